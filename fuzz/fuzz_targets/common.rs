@@ -0,0 +1,35 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use sokoban::question::{Question, QuestionCollection};
+use sokoban::solve::{self, Algorithm, SolverConfig};
+use sokoban::stats::LevelStats;
+
+/// Writes out, measures and simplifies `question`, and if it is valid, tries to solve
+/// it for a moment. Any solution found has to work.
+pub fn check_level(question: &Question) {
+    let _ = Question::from_str(&question.to_string());
+    let _ = question.simplify();
+    let _ = LevelStats::new(question);
+    if question.validate().is_err() {
+        return;
+    }
+
+    let config = SolverConfig {
+        algorithm: Algorithm::AStar,
+        max_nodes: Some(50),
+        time_limit: Some(Duration::from_millis(20)),
+        ..SolverConfig::default()
+    };
+    if let Some(solution) = solve::solve_puzzle(question, &config).solution {
+        assert_eq!(solution.verify(question), Ok(()), "{}", question);
+    }
+}
+
+pub fn check_collection(collection: &QuestionCollection) {
+    for question in collection.iter() {
+        check_level(question);
+    }
+    let _ = collection.to_string();
+    let _ = collection.to_rle();
+}
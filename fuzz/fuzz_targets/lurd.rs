@@ -0,0 +1,22 @@
+#![no_main]
+
+use std::str::FromStr;
+
+use libfuzzer_sys::fuzz_target;
+use sokoban::question::{Question, Symmetry};
+use sokoban::solve::Solution;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(solution) = Solution::from_str(text) else {
+        return;
+    };
+    // Two boxes side by side, so that moves can push boxes into walls and each other.
+    let question = Question::from_str("#######\n#     #\n#@$ $.#\n#   . #\n#######").unwrap();
+    let _ = solution.verify(&question);
+    let _ = solution.replay(&question);
+    let _ = solution.to_string();
+    let _ = solution.transform(Symmetry::Rotate90).verify(&question);
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use std::str::FromStr;
+
+use libfuzzer_sys::fuzz_target;
+use sokoban::question::{Question, QuestionCollection};
+
+mod common;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(question) = Question::from_str(text) {
+        common::check_level(&question);
+    }
+    if let Ok(collection) = QuestionCollection::from_str(text) {
+        common::check_collection(&collection);
+    }
+});
@@ -1,19 +1,13 @@
 use std::env;
-use std::error::Error;
+use std::process;
 
-use sokoban::{reader, solve};
+use sokoban::cli;
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() {
     let args = env::args().collect::<Vec<_>>();
 
-    let config = reader::Config::new(&args)?;
-
-    let puzzles = reader::read(&config)?;
-
-    match config.question_number {
-        Some(n) => solve::solve_puzzle(&puzzles[n]),
-        None => solve::solve_collection(&puzzles),
+    if let Err(err) = cli::run(&args) {
+        eprintln!("error: {}", err);
+        process::exit(1);
     }
-
-    Ok(())
 }
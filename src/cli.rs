@@ -0,0 +1,1039 @@
+//! The command line interface of the `sokoban` binary.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::generate::{self, GenerateConfig, LayoutStyle};
+use crate::json;
+use crate::question::{Question, QuestionCollection, SavedSolution};
+use crate::reader::{self, slc, sok};
+use crate::render::{self, Theme};
+use crate::solve::{
+    self, Algorithm, Difficulty, OptimizeConfig, RateError, Record, RecordFormat, Solution,
+    SolveResult, SolverConfig,
+};
+use crate::stats::{CollectionStats, LevelStats};
+
+mod play;
+mod select;
+
+const USAGE: &str = "\
+usage: sokoban <command> <levels> [options]
+       sokoban generate [options]
+       sokoban <levels> [index]
+
+<levels> is a file, a directory, a pattern such as 'levels/*.sok', or - for stdin.
+
+commands:
+  solve      solve levels and print their solutions
+  verify     check the solutions saved with levels, or one given with --solution
+  stats      measure levels without solving them: size, floor, rooms and more
+  convert    write levels out in another format
+  render     print levels as text, or draw them and their solutions as pictures
+  bench      time the solver on levels
+  rate       solve levels and rate how hard they are
+  play       play levels in the terminal
+  generate   make new levels, each with a solution
+
+options:
+  -l, --level <list>        levels to use, counting from 1, eg. 3 or 1,4-6 or 10-
+      --title <text>        levels whose title is or contains <text>
+  -a, --algorithm <name>    bfs, astar or greedy (default bfs)
+      --max-nodes <n>       give up on a level after expanding <n> positions
+  -t, --time-limit <time>   give up on a level after <time>, eg. 30, 2.5s or 500ms
+  -j, --threads <n>         solve this many levels at once (default: every core)
+      --optimize            shorten solutions after they are found
+      --progress <n>        print the position being searched every <n> positions
+      --simplify            wall off floor and boxes that don't matter to solving
+                            levels first; solutions still work on the originals
+  -f, --format <format>     solve: text, lurd, sok, jsonl or csv
+                            convert and generate: xsb, rle, slc or sok
+                            render: text, svg, gif or apng
+                            rate: text or jsonl
+                            stats: text or json
+  -o, --output <file>       write to <file> instead of stdout
+      --solution <lurd>     the solution to verify or draw (default: the first saved)
+      --theme <name>        render: classic, dark or print (default classic)
+      --frames              render: draw the level after every push of the solution,
+                            in an animation instead of after every move
+      --path                render: draw the pushes of the solution as arrows
+      --tile <pixels>       render: the size of each square (default 32)
+      --delay <time>        render: how long each frame is shown (default 100ms)
+      --count <n>           generate: how many levels to make (default 1)
+      --seed <n>            generate: the seed of the first level, the next gets <n>+1
+                            and so on (default: from the clock)
+      --width <n>           generate: the width of levels, walls included (default 8)
+      --height <n>          generate: the height of levels, walls included (default 8)
+      --boxes <n>           generate: the number of boxes (default 3)
+      --min-pushes <n>      generate: only keep levels whose solution has at least
+                            <n> pushes (default 10)
+      --min-interest <x>    generate: only keep levels at least this interesting;
+                            0 keeps boring ones too (default 3)
+      --layout <style>      generate: templates or walk (default templates)
+      --repeat <n>          bench: solve each level <n> times (default 3)
+      --sort                rate: list the easiest levels first
+
+The second form solves the level at position [index], counting from 0, or every
+level if it is left out.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Solve,
+    Verify,
+    Stats,
+    Convert,
+    Render,
+    Bench,
+    Rate,
+    Play,
+    Generate,
+}
+
+impl Command {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "solve" => Some(Self::Solve),
+            "verify" => Some(Self::Verify),
+            "stats" => Some(Self::Stats),
+            "convert" => Some(Self::Convert),
+            "render" => Some(Self::Render),
+            "bench" => Some(Self::Bench),
+            "rate" => Some(Self::Rate),
+            "play" => Some(Self::Play),
+            "generate" => Some(Self::Generate),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Solve => "solve",
+            Self::Verify => "verify",
+            Self::Stats => "stats",
+            Self::Convert => "convert",
+            Self::Render => "render",
+            Self::Bench => "bench",
+            Self::Rate => "rate",
+            Self::Play => "play",
+            Self::Generate => "generate",
+        }
+    }
+
+    /// The options this command makes use of, besides the level selection.
+    fn options(&self) -> &'static [&'static str] {
+        match self {
+            Self::Solve => &[
+                "algorithm",
+                "max-nodes",
+                "time-limit",
+                "threads",
+                "optimize",
+                "progress",
+                "simplify",
+                "format",
+                "output",
+            ],
+            Self::Verify => &["solution"],
+            Self::Stats => &["format", "output", "simplify"],
+            Self::Render => &[
+                "format", "output", "solution", "theme", "frames", "path", "tile", "delay",
+            ],
+            Self::Convert => &["format", "output", "simplify"],
+            Self::Bench => &["algorithm", "max-nodes", "time-limit", "repeat", "simplify"],
+            Self::Rate => &[
+                "algorithm",
+                "max-nodes",
+                "time-limit",
+                "threads",
+                "sort",
+                "simplify",
+                "format",
+                "output",
+            ],
+            Self::Play => &[],
+            Self::Generate => &[
+                "count",
+                "seed",
+                "width",
+                "height",
+                "boxes",
+                "min-pushes",
+                "min-interest",
+                "layout",
+                "format",
+                "output",
+            ],
+        }
+    }
+}
+
+/// Options that don't take a value.
+const SWITCHES: [&str; 6] = ["optimize", "frames", "path", "sort", "simplify", "help"];
+
+/// Returns the long name of an option given by its short name.
+fn long_name(short: &str) -> Option<&'static str> {
+    match short {
+        "l" => Some("level"),
+        "a" => Some("algorithm"),
+        "t" => Some("time-limit"),
+        "j" => Some("threads"),
+        "f" => Some("format"),
+        "o" => Some("output"),
+        "h" => Some("help"),
+        _ => None,
+    }
+}
+
+struct Options {
+    command: Command,
+    path: String,
+    levels: Option<String>,
+    title: Option<String>,
+    solver: SolverConfig,
+    threads: usize,
+    optimize: bool,
+    format: Option<String>,
+    output: Option<String>,
+    solution: Option<Solution>,
+    repeat: usize,
+    theme: Theme,
+    frames: bool,
+    push_path: bool,
+    /// How many pixels across each square is drawn.
+    tile: usize,
+    /// How long each frame of an animation is shown.
+    delay: Duration,
+    generator: GenerateConfig,
+    /// The number of levels to generate.
+    count: usize,
+    sort: bool,
+    simplify: bool,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Option<Self>, Box<dyn Error>> {
+        let mut args = args.iter().skip(1).map(String::as_str).peekable();
+
+        let command = match args.peek().map(|arg| Command::from_name(arg)) {
+            Some(Some(command)) => {
+                args.next();
+                command
+            }
+            Some(None) if !matches!(args.peek(), Some(&"help")) => Command::Solve,
+            _ => return Ok(None),
+        };
+
+        let mut options = Self {
+            command,
+            path: String::new(),
+            levels: None,
+            title: None,
+            solver: SolverConfig::default(),
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            optimize: false,
+            format: None,
+            output: None,
+            solution: None,
+            repeat: 3,
+            theme: Theme::default(),
+            frames: false,
+            push_path: false,
+            tile: 32,
+            delay: Duration::from_millis(100),
+            generator: GenerateConfig {
+                seed: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_secs()),
+                ..GenerateConfig::default()
+            },
+            count: 1,
+            sort: false,
+            simplify: false,
+        };
+
+        let mut positional = vec![];
+        while let Some(arg) = args.next() {
+            let (name, inline) = if let Some(long) = arg.strip_prefix("--") {
+                match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value)),
+                    None => (long, None),
+                }
+            } else if arg.len() > 1 && arg.starts_with('-') {
+                let name = long_name(&arg[1..]).ok_or_else(|| format!("unknown option {}", arg))?;
+                (name, None)
+            } else {
+                positional.push(arg);
+                continue;
+            };
+
+            if name == "help" {
+                return Ok(None);
+            }
+            if !["level", "title"].contains(&name) && !command.options().contains(&name) {
+                return Err(format!("unknown option --{} for {}", name, command.name()).into());
+            }
+
+            let value = if SWITCHES.contains(&name) {
+                ""
+            } else {
+                match inline {
+                    Some(value) => value,
+                    None => args
+                        .next()
+                        .ok_or_else(|| format!("--{} needs a value", name))?,
+                }
+            };
+            options.set(name, value)?;
+        }
+
+        match positional[..] {
+            [] if command == Command::Generate => (),
+            [path] if command != Command::Generate => options.path = path.to_string(),
+            [path] => return Err(format!("generate doesn't take levels, got {:?}", path).into()),
+            // The original `sokoban <file> <index>` form, where the index counts from 0.
+            [path, index] if command == Command::Solve && options.levels.is_none() => {
+                let index = index
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid level index {:?}", index))?;
+                options.path = path.to_string();
+                options.levels = Some((index + 1).to_string());
+            }
+            [] => return Err("no levels given".into()),
+            _ => return Err(format!("unexpected argument {:?}", positional[1]).into()),
+        }
+
+        Ok(Some(options))
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), Box<dyn Error>> {
+        let number = |value: &str| {
+            value
+                .parse::<usize>()
+                .map_err(|_| format!("--{} needs a number, got {:?}", name, value))
+        };
+
+        match name {
+            "level" => self.levels = Some(value.to_string()),
+            "title" => self.title = Some(value.to_string()),
+            "algorithm" => self.solver.algorithm = Algorithm::from_str(value)?,
+            "max-nodes" => self.solver.max_nodes = Some(number(value)?),
+            "time-limit" => self.solver.time_limit = Some(parse_duration(value)?),
+            "progress" => self.solver.progress = Some(number(value)?.max(1)),
+            "threads" => self.threads = number(value)?.max(1),
+            "optimize" => self.optimize = true,
+            "format" => self.format = Some(value.to_ascii_lowercase()),
+            "output" => self.output = Some(value.to_string()),
+            "solution" => self.solution = Some(Solution::from_str(value)?),
+            "repeat" => self.repeat = number(value)?.max(1),
+            "theme" => self.theme = Theme::from_str(value)?,
+            "frames" => self.frames = true,
+            "path" => self.push_path = true,
+            "tile" => self.tile = number(value)?.clamp(1, 256),
+            "delay" => self.delay = parse_duration(value)?,
+            "count" => self.count = number(value)?,
+            "seed" => {
+                self.generator.seed = value
+                    .parse()
+                    .map_err(|_| format!("--seed needs a number, got {:?}", value))?
+            }
+            "width" => self.generator.width = number(value)?,
+            "height" => self.generator.height = number(value)?,
+            "boxes" => self.generator.boxes = number(value)?,
+            "min-pushes" => self.generator.min_pushes = number(value)?,
+            "min-interest" => {
+                self.generator.min_interest = value
+                    .parse()
+                    .map_err(|_| format!("--min-interest needs a number, got {:?}", value))?
+            }
+            "sort" => self.sort = true,
+            "simplify" => self.simplify = true,
+            "layout" => self.generator.layout = LayoutStyle::from_str(value)?,
+            _ => unreachable!("option --{} is not handled", name),
+        }
+        Ok(())
+    }
+
+    /// Returns the format to write in, checking that it is one of `formats`. The
+    /// first format is the default.
+    fn format(&self, formats: &[&'static str]) -> Result<&'static str, Box<dyn Error>> {
+        match &self.format {
+            None => Ok(formats[0]),
+            Some(format) => formats
+                .iter()
+                .find(|&&f| f == format)
+                .copied()
+                .ok_or_else(|| {
+                    format!(
+                        "unknown format {:?} for {}, expected one of {}",
+                        format,
+                        self.command.name(),
+                        formats.join(", ")
+                    )
+                    .into()
+                }),
+        }
+    }
+
+    fn writer(&self) -> io::Result<Box<dyn Write>> {
+        Ok(match &self.output {
+            Some(path) if path != "-" => Box::new(BufWriter::new(File::create(path)?)),
+            _ => Box::new(BufWriter::new(io::stdout())),
+        })
+    }
+}
+
+/// Parses a length of time such as `30`, `2.5s`, `500ms` or `2m`. Plain numbers are seconds.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (number, scale) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        (secs, 1.0)
+    } else if let Some(mins) = s.strip_suffix('m') {
+        (mins, 60.0)
+    } else {
+        (s, 1.0)
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite() && *n >= 0.0)
+        .map(|n| Duration::from_secs_f64(n * scale))
+        .ok_or_else(|| format!("invalid length of time {:?}", s))
+}
+
+/// Runs the command line `args`, including the program name.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let Some(options) = Options::parse(args)? else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+
+    if options.command == Command::Generate {
+        return generate(&options);
+    }
+
+    let mut collection = reader::read_path(&options.path)?;
+    let levels = select_levels(&options, &collection)?;
+    let original = options.simplify.then(|| collection.clone());
+    if options.simplify {
+        simplify(&mut collection, &levels);
+    }
+    let original = original.as_ref().unwrap_or(&collection);
+
+    match options.command {
+        Command::Solve => solve(&options, original, &collection, &levels),
+        Command::Verify => verify(&options, &collection, &levels),
+        Command::Stats => stats(&options, &collection, &levels),
+        Command::Convert => convert(&options, &collection, &levels),
+        Command::Render => render(&options, &collection, &levels),
+        Command::Bench => bench(&options, &collection, &levels),
+        Command::Rate => rate(&options, &collection, &levels),
+        Command::Play => play::play(&collection, &levels),
+        Command::Generate => unreachable!("generate doesn't read levels"),
+    }
+}
+
+/// Replaces the levels at `levels` with simplified ones, saying what was taken away.
+fn simplify(collection: &mut QuestionCollection, levels: &[usize]) {
+    for (idx, question) in collection.iter_mut().enumerate() {
+        if !levels.contains(&idx) {
+            continue;
+        }
+        let simplified = question.simplify();
+        if simplified.removed_floor > 0 || simplified.fixed_boxes > 0 {
+            eprintln!(
+                "level {}: walled off {} floor squares and {} fixed boxes",
+                idx + 1,
+                simplified.removed_floor,
+                simplified.fixed_boxes
+            );
+        }
+        *question = simplified.question;
+    }
+}
+
+/// Returns the indices of the levels picked by `--level` and `--title`, or every level.
+fn select_levels(
+    options: &Options,
+    collection: &QuestionCollection,
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    let mut levels = match &options.levels {
+        Some(levels) => select::parse_levels(levels, collection)?,
+        None => (0..collection.len()).collect(),
+    };
+    if let Some(title) = &options.title {
+        let titled = select::find_titles(title, collection)?;
+        levels.retain(|idx| titled.contains(idx));
+    }
+
+    if levels.is_empty() {
+        Err("no levels selected".into())
+    } else {
+        Ok(levels)
+    }
+}
+
+/// Returns a short description of a level, such as `level 3 "Title"`.
+fn describe(collection: &QuestionCollection, idx: usize) -> String {
+    match collection[idx].title() {
+        Some(title) => format!("level {} {:?}", idx + 1, title),
+        None => format!("level {}", idx + 1),
+    }
+}
+
+/// Returns a new collection holding just `levels`, along with the collection's metadata.
+fn sub_collection(collection: &QuestionCollection, levels: &[usize]) -> QuestionCollection {
+    let mut selected =
+        QuestionCollection::new(levels.iter().map(|&idx| collection[idx].clone()).collect());
+    for (key, value) in collection.metadata() {
+        selected.set_metadata(key, value.clone());
+    }
+    selected
+}
+
+/// Solves the levels at `levels` of `collection`. Solutions written in the sok format
+/// go with the levels of `original`, which `collection` may be simplified from.
+fn solve(
+    options: &Options,
+    original: &QuestionCollection,
+    collection: &QuestionCollection,
+    levels: &[usize],
+) -> Result<(), Box<dyn Error>> {
+    let format = options.format(&["text", "lurd", "sok", "jsonl", "csv"])?;
+    let records = RecordFormat::from_str(format).ok();
+    let selected = sub_collection(collection, levels);
+    let mut out = options.writer()?;
+
+    if let Some(header) = records.and_then(|records| records.header()) {
+        writeln!(out, "{}", header)?;
+    }
+
+    let mut solutions = vec![];
+    let mut error = None;
+    solve::solve_collection(
+        &selected,
+        &options.solver,
+        options.threads,
+        |idx, mut result| {
+            let level = levels[idx];
+            if options.optimize {
+                if let Some(solution) = &result.solution {
+                    match solve::optimize_solution(
+                        &selected[idx],
+                        solution,
+                        &OptimizeConfig::default(),
+                    ) {
+                        Ok(better) => result.solution = Some(better),
+                        Err(err) => eprintln!(
+                            "{}: couldn't optimize the solution: {}",
+                            describe(collection, level),
+                            err
+                        ),
+                    }
+                }
+            }
+
+            let line = match (format, records) {
+                (_, Some(records)) => {
+                    Some(records.write(&Record::new(level, &collection[level], &result)))
+                }
+                ("text", _) => Some(describe_result(&describe(collection, level), &result)),
+                ("lurd", _) => Some(
+                    result
+                        .solution
+                        .as_ref()
+                        .map(Solution::to_string)
+                        .unwrap_or_default(),
+                ),
+                _ => None,
+            };
+            if let Some(line) = line {
+                if let Err(err) = writeln!(out, "{}", line).and_then(|_| out.flush()) {
+                    error.get_or_insert(err);
+                }
+            }
+            solutions.push(result.solution);
+        },
+    );
+    if let Some(err) = error {
+        return Err(err.into());
+    }
+
+    if format == "sok" {
+        // Simplifying only walls off squares, so the solutions play the same on the
+        // levels they were simplified from.
+        let mut originals = sub_collection(original, levels);
+        for (question, solution) in originals.iter_mut().zip(solutions) {
+            if let Some(solution) = solution {
+                question.add_solution(SavedSolution::from(&solution));
+            }
+        }
+        out.write_all(sok::to_string(&originals).as_bytes())?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Describes how solving a level went, followed by the solution on the next line.
+fn describe_result(name: &str, result: &SolveResult) -> String {
+    let stats = format!(
+        "{} expanded, {} visited, {:.2}s",
+        result.expanded,
+        result.visited,
+        result.elapsed.as_secs_f64()
+    );
+    match &result.solution {
+        Some(solution) => format!(
+            "{}: solved in {} moves, {} pushes ({})\n{}",
+            name,
+            solution.move_count(),
+            solution.push_count(),
+            stats,
+            solution
+        ),
+        None => format!("{}: {} ({})", name, result.status, stats),
+    }
+}
+
+fn verify(
+    options: &Options,
+    collection: &QuestionCollection,
+    levels: &[usize],
+) -> Result<(), Box<dyn Error>> {
+    let (mut checked, mut failed) = (0, 0);
+    for &idx in levels {
+        let question = &collection[idx];
+        let name = describe(collection, idx);
+
+        let solutions = match &options.solution {
+            Some(solution) => vec![solution.clone()],
+            None => question
+                .solutions()
+                .iter()
+                .filter(|saved| !saved.snapshot)
+                .map(|saved| Solution::from_str(&saved.lurd))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| format!("{}: {}", name, err))?,
+        };
+        if solutions.is_empty() {
+            println!("{}: no saved solutions", name);
+            continue;
+        }
+
+        for (n, solution) in solutions.iter().enumerate() {
+            checked += 1;
+            match solution.verify(question) {
+                Ok(()) => println!(
+                    "{}: solution {} ok, {} moves, {} pushes",
+                    name,
+                    n + 1,
+                    solution.move_count(),
+                    solution.push_count()
+                ),
+                Err(err) => {
+                    failed += 1;
+                    println!("{}: solution {} failed, {}", name, n + 1, err);
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        Err(format!("{} of {} solutions failed", failed, checked).into())
+    } else {
+        Ok(())
+    }
+}
+
+fn stats(
+    options: &Options,
+    collection: &QuestionCollection,
+    levels: &[usize],
+) -> Result<(), Box<dyn Error>> {
+    let format = options.format(&["text", "json"])?;
+    let measured = levels
+        .iter()
+        .map(|&idx| LevelStats::new(&collection[idx]))
+        .collect::<Vec<_>>();
+    let total = CollectionStats::new(&measured);
+    let mut out = options.writer()?;
+
+    if format == "json" {
+        let levels = levels
+            .iter()
+            .zip(&measured)
+            .map(|(&idx, stats)| {
+                json::Object::new()
+                    .number("level", idx + 1)
+                    .optional("title", collection[idx].title())
+                    .raw("stats", stats.to_json())
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        let report = json::Object::new()
+            .raw("levels", format!("[{}]", levels.join(",")))
+            .raw("collection", total.to_json());
+        writeln!(out, "{}", report)?;
+        out.flush()?;
+        return Ok(());
+    }
+
+    writeln!(
+        out,
+        "{:>6}  {:>7}  {:>5}  {:>5}  {:>5}  {:>5}  {:>7}  {:>5}  {:>4}  {:>5}  title",
+        "level", "size", "boxes", "floor", "reach", "dead", "tunnels", "rooms", "goal", "bound"
+    )?;
+    for (&idx, stats) in levels.iter().zip(&measured) {
+        writeln!(
+            out,
+            "{:>6}  {:>7}  {:>5}  {:>5}  {:>5}  {:>5}  {:>7}  {:>5}  {:>4}  {:>5}  {}",
+            idx + 1,
+            format!("{}x{}", stats.width, stats.height),
+            stats.boxes,
+            stats.floor,
+            stats.reachable,
+            stats.dead,
+            stats.tunnels,
+            stats.rooms,
+            if stats.goal_room { "yes" } else { "no" },
+            stats
+                .push_lower_bound
+                .map_or("-".to_string(), |n| n.to_string()),
+            collection[idx].title().unwrap_or_default()
+        )?;
+    }
+    writeln!(
+        out,
+        "{} levels, {} boxes (at most {} in one), at most {}x{}, {:.1} reachable and {:.1} \
+         dead squares on average, {} with a goal room, {} pushes at least{}",
+        total.levels,
+        total.boxes,
+        total.max_boxes,
+        total.max_width,
+        total.max_height,
+        total.mean_reachable,
+        total.mean_dead,
+        total.goal_rooms,
+        total.push_lower_bound,
+        match total.unsolvable {
+            0 => String::new(),
+            n => format!(", {} unsolvable", n),
+        }
+    )?;
+    out.flush()?;
+    Ok(())
+}
+
+fn convert(
+    options: &Options,
+    collection: &QuestionCollection,
+    levels: &[usize],
+) -> Result<(), Box<dyn Error>> {
+    write_collection(options, &sub_collection(collection, levels))
+}
+
+/// Writes out `collection` in the format picked with `--format`.
+fn write_collection(
+    options: &Options,
+    collection: &QuestionCollection,
+) -> Result<(), Box<dyn Error>> {
+    let text = match options.format(&["xsb", "rle", "slc", "sok"])? {
+        "xsb" => format!("{}\n", collection),
+        "rle" => format!("{}\n", collection.to_rle().trim_end()),
+        "slc" => slc::to_string(collection),
+        _ => sok::to_string(collection),
+    };
+
+    let mut out = options.writer()?;
+    out.write_all(text.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+fn generate(options: &Options) -> Result<(), Box<dyn Error>> {
+    let mut collection = QuestionCollection::new(vec![]);
+    for n in 0..options.count as u64 {
+        let config = GenerateConfig {
+            seed: options.generator.seed.wrapping_add(n),
+            ..options.generator.clone()
+        };
+        let question =
+            generate::generate(&config).map_err(|err| format!("seed {}: {}", config.seed, err))?;
+        collection.push(question);
+    }
+    write_collection(options, &collection)
+}
+
+fn render(
+    options: &Options,
+    collection: &QuestionCollection,
+    levels: &[usize],
+) -> Result<(), Box<dyn Error>> {
+    let format = options.format(&["text", "svg", "gif", "apng"])?;
+    if format == "text" {
+        if options.frames || options.push_path {
+            return Err("--frames and --path can't be used with --format text".into());
+        }
+        let mut out = options.writer()?;
+        for &idx in levels {
+            writeln!(out, "{}", describe(collection, idx))?;
+            writeln!(out, "{}\n", collection[idx].grid_lines().join("\n"))?;
+        }
+        out.flush()?;
+        return Ok(());
+    }
+    if format != "svg" && options.push_path {
+        return Err("--path can only be used with --format svg".into());
+    }
+
+    // The images to write, each with the level and frame it shows.
+    let mut images = vec![];
+    for &idx in levels {
+        let question = &collection[idx];
+        let name = describe(collection, idx);
+        let fail = |err| format!("{}: {}", name, err);
+        let (theme, tile) = (&options.theme, options.tile);
+
+        if format != "svg" {
+            let solution = level_solution(options, question, &name)?;
+            let scenes = if options.frames {
+                render::push_frames(question, &solution).map_err(fail)?
+            } else {
+                render::move_frames(question, &solution).map_err(fail)?
+            };
+            let image = match format {
+                "gif" => render::to_gif(&scenes, theme, tile, options.delay),
+                _ => render::to_apng(&scenes, theme, tile, options.delay),
+            };
+            images.push((idx, None, image));
+            continue;
+        }
+
+        if !options.frames && !options.push_path {
+            let scene = render::Scene::new(question);
+            images.push((idx, None, render::to_svg(&scene, theme, tile).into_bytes()));
+            continue;
+        }
+
+        let solution = level_solution(options, question, &name)?;
+        let paths = if options.push_path {
+            render::push_paths(question, &solution).map_err(fail)?
+        } else {
+            vec![]
+        };
+        let scenes = if options.frames {
+            render::push_frames(question, &solution).map_err(fail)?
+        } else {
+            vec![render::Scene::new(question)]
+        };
+        for (frame, scene) in scenes.iter().enumerate() {
+            let svg = render::to_svg_with_paths(scene, theme, tile, &paths);
+            images.push((idx, options.frames.then_some(frame), svg.into_bytes()));
+        }
+    }
+
+    if let [(_, _, image)] = &images[..] {
+        let mut out = options.writer()?;
+        out.write_all(image)?;
+        out.flush()?;
+        return Ok(());
+    }
+
+    // Several images are written to files named after the output, such as `out-2-005.svg`.
+    let output = match options.output.as_deref() {
+        Some(output) if output != "-" => output,
+        _ => return Err("drawing more than one image needs --output <file>".into()),
+    };
+    let extension = format!(".{}", if format == "apng" { "png" } else { format });
+    let stem = output.strip_suffix(&extension).unwrap_or(output);
+    for (idx, frame, image) in images {
+        let mut path = stem.to_string();
+        if levels.len() > 1 {
+            path += &format!("-{}", idx + 1);
+        }
+        if let Some(frame) = frame {
+            path += &format!("-{:03}", frame);
+        }
+        path += &extension;
+        std::fs::write(&path, image)?;
+        println!("wrote {}", path);
+    }
+    Ok(())
+}
+
+/// Returns the solution given with `--solution`, or else the first one saved with the level.
+fn level_solution(
+    options: &Options,
+    question: &Question,
+    name: &str,
+) -> Result<Solution, Box<dyn Error>> {
+    if let Some(solution) = &options.solution {
+        return Ok(solution.clone());
+    }
+    let saved = question
+        .solutions()
+        .iter()
+        .find(|saved| !saved.snapshot)
+        .ok_or_else(|| format!("{}: no solution to draw, give one with --solution", name))?;
+    Ok(Solution::from_str(&saved.lurd).map_err(|err| format!("{}: {}", name, err))?)
+}
+
+fn rate(
+    options: &Options,
+    collection: &QuestionCollection,
+    levels: &[usize],
+) -> Result<(), Box<dyn Error>> {
+    let format = options.format(&["text", "jsonl"])?;
+    let selected = sub_collection(collection, levels);
+    let mut ratings = vec![];
+    solve::solve_collection(
+        &selected,
+        &options.solver,
+        options.threads,
+        |idx, result| {
+            let rating = match &result.solution {
+                Some(solution) => Difficulty::measure(&selected[idx], solution, result.expanded)
+                    .map_err(RateError::Replay),
+                None => Err(RateError::Unsolved(result.status)),
+            };
+            if let Err(err @ RateError::Replay(_)) = &rating {
+                eprintln!("{}: {}", describe(collection, levels[idx]), err);
+            }
+            ratings.push((levels[idx], rating));
+        },
+    );
+    if options.sort {
+        // Levels that couldn't be rated go last.
+        ratings.sort_by(|(_, a), (_, b)| match (a, b) {
+            (Ok(a), Ok(b)) => a.score.total_cmp(&b.score),
+            _ => a.is_err().cmp(&b.is_err()),
+        });
+    }
+
+    let mut out = options.writer()?;
+    if format == "text" {
+        writeln!(
+            out,
+            "{:>6}  {:<8} {:>6} {:>6} {:>6} {:>9} {:>6} {:>5} {:>8} {:>9}  title",
+            "level",
+            "tier",
+            "score",
+            "pushes",
+            "moves",
+            "expanded",
+            "branch",
+            "turns",
+            "switches",
+            "deadlocks"
+        )?;
+    }
+    for (level, rating) in ratings {
+        let title = collection[level].title();
+        if format == "jsonl" {
+            let object = json::Object::new()
+                .number("level", level + 1)
+                .optional("title", title);
+            let object = match &rating {
+                Ok(difficulty) => object
+                    .string("status", "solved")
+                    .raw("difficulty", difficulty.to_json()),
+                Err(RateError::Unsolved(status)) => object
+                    .string("status", &status.to_string())
+                    .raw("difficulty", "null"),
+                Err(err @ RateError::Replay(_)) => object
+                    .string("status", "error")
+                    .string("error", &err.to_string())
+                    .raw("difficulty", "null"),
+            };
+            writeln!(out, "{}", object)?;
+            continue;
+        }
+
+        match rating {
+            Ok(d) => writeln!(
+                out,
+                "{:>6}  {:<8} {:>6.1} {:>6} {:>6} {:>9} {:>6.2} {:>5} {:>8} {:>8.1}%  {}",
+                level + 1,
+                d.tier,
+                d.score,
+                d.pushes,
+                d.moves,
+                d.expanded,
+                d.branching,
+                d.direction_changes,
+                d.box_switches,
+                d.deadlock_density * 100.0,
+                title.unwrap_or_default()
+            )?,
+            Err(err) => writeln!(
+                out,
+                "{:>6}  {:<8} {:>6}  {}",
+                level + 1,
+                match err {
+                    RateError::Unsolved(status) => status.to_string(),
+                    RateError::Replay(_) => "error".to_string(),
+                },
+                "-",
+                title.unwrap_or_default()
+            )?,
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn bench(
+    options: &Options,
+    collection: &QuestionCollection,
+    levels: &[usize],
+) -> Result<(), Box<dyn Error>> {
+    let (mut total_time, mut total_expanded, mut solved) = (Duration::ZERO, 0, 0);
+    for &idx in levels {
+        let results = (0..options.repeat)
+            .map(|_| solve::solve_puzzle(&collection[idx], &options.solver))
+            .collect::<Vec<_>>();
+
+        let best = results.iter().map(|result| result.elapsed).min().unwrap();
+        let time = results
+            .iter()
+            .map(|result| result.elapsed)
+            .sum::<Duration>();
+        let mean = time / options.repeat as u32;
+        let result = &results[0];
+        let pushes = result
+            .solution
+            .as_ref()
+            .map_or("-".to_string(), |solution| {
+                solution.push_count().to_string()
+            });
+
+        println!(
+            "{}: {}, {} pushes, {} expanded, best {:.3}s, mean {:.3}s, {:.0} nodes/s",
+            describe(collection, idx),
+            result.status,
+            pushes,
+            result.expanded,
+            best.as_secs_f64(),
+            mean.as_secs_f64(),
+            result.expanded as f64 / mean.as_secs_f64().max(1e-9)
+        );
+
+        total_time += mean;
+        total_expanded += result.expanded;
+        solved += usize::from(result.solution.is_some());
+    }
+
+    println!(
+        "total: {}/{} solved, {} expanded, {:.3}s, {:.0} nodes/s",
+        solved,
+        levels.len(),
+        total_expanded,
+        total_time.as_secs_f64(),
+        total_expanded as f64 / total_time.as_secs_f64().max(1e-9)
+    );
+    Ok(())
+}
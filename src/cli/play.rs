@@ -0,0 +1,278 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::question::QuestionCollection;
+use crate::solve::{Algorithm, Dir, Game, Hint, Move, SolverConfig, StepOutcome};
+
+const KEYS: &str = "\
+arrows/lurd: move   z: undo   y: redo   x: restart
+n/p: next/previous level   h: hint   q/ctrl-c: quit";
+
+/// Puts the terminal into a mode where keys are read as soon as they are pressed,
+/// without being echoed, and puts it back when dropped.
+///
+/// Ctrl-C is read as a key too, rather than killing the game before the terminal is
+/// put back.
+///
+/// Uses `stty`, so does nothing if stdin isn't a terminal or `stty` isn't around,
+/// in which case keys are read a line at a time.
+struct Terminal {
+    saved: Option<String>,
+}
+
+impl Terminal {
+    fn new() -> Self {
+        let saved = if io::stdin().is_terminal() {
+            stty(&["-g"]).filter(|_| stty(&["-icanon", "-echo", "-isig", "min", "1"]).is_some())
+        } else {
+            None
+        };
+        Self { saved }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        if let Some(saved) = &self.saved {
+            stty(&[saved.trim()]);
+        }
+    }
+}
+
+/// Runs `stty` on the controlling terminal, returning its output if it succeeded.
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(File::open("/dev/tty").ok()?)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Move(Dir),
+    Undo,
+    Redo,
+    Restart,
+    Next,
+    Previous,
+    Hint,
+    Quit,
+}
+
+/// The byte that starts an escape sequence, such as the ones sent by arrow keys.
+const ESCAPE: u8 = 0x1b;
+
+/// Returns the key that `byte` stands for on its own, if any.
+fn key_for(byte: u8) -> Option<Key> {
+    let key = match byte {
+        b'z' | b'Z' | 0x08 | 0x7f => Key::Undo,
+        b'y' | b'Y' => Key::Redo,
+        b'x' | b'X' => Key::Restart,
+        b'n' | b'N' => Key::Next,
+        b'p' | b'P' => Key::Previous,
+        b'h' | b'H' => Key::Hint,
+        // Ctrl-C and Ctrl-D.
+        b'q' | b'Q' | 0x03 | 0x04 => Key::Quit,
+        byte => Key::Move(Dir::from_lurd(byte as char)?.0),
+    };
+    Some(key)
+}
+
+/// Returns the direction of the arrow key whose escape sequence ends in `byte`.
+fn arrow_for(byte: u8) -> Option<Dir> {
+    match byte {
+        b'A' => Some(Dir::North),
+        b'B' => Some(Dir::South),
+        b'C' => Some(Dir::East),
+        b'D' => Some(Dir::West),
+        _ => None,
+    }
+}
+
+/// Reads keys from stdin, turning arrow key escape sequences into moves.
+struct Keys<R> {
+    bytes: io::Bytes<R>,
+}
+
+impl<R: Read> Iterator for Keys<R> {
+    type Item = io::Result<Key>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let byte = match self.bytes.next()? {
+                Ok(byte) => byte,
+                Err(err) => return Some(Err(err)),
+            };
+
+            let key = if byte == ESCAPE {
+                // Arrow keys are sent as `ESC [ A` or `ESC O A`. Otherwise Esc was pressed
+                // on its own, which does nothing, and the next byte is a key of its own.
+                match self.bytes.next()? {
+                    Ok(b'[' | b'O') => match self.bytes.next()? {
+                        Ok(byte) => arrow_for(byte).map(Key::Move),
+                        Err(_) => continue,
+                    },
+                    Ok(byte) => key_for(byte),
+                    Err(err) => return Some(Err(err)),
+                }
+            } else {
+                key_for(byte)
+            };
+            if let Some(key) = key {
+                return Some(Ok(key));
+            }
+        }
+    }
+}
+
+/// Plays the `levels` of `collection` in the terminal, starting with the first.
+pub fn play(collection: &QuestionCollection, levels: &[usize]) -> Result<(), Box<dyn Error>> {
+    let terminal = Terminal::new();
+    let mut out = io::stdout();
+
+    let hint_config = SolverConfig {
+        algorithm: Algorithm::Greedy,
+        time_limit: Some(Duration::from_secs(5)),
+        ..SolverConfig::default()
+    };
+
+    let mut current = 0;
+    let mut game = Game::new(&collection[levels[current]]);
+    let mut message = String::new();
+    let keys = Keys {
+        bytes: io::stdin().lock().bytes(),
+    };
+
+    draw(&mut out, collection, levels[current], &game, &message)?;
+    for key in keys {
+        message.clear();
+        let key = key?;
+        match key {
+            Key::Move(dir) => {
+                if !game.is_solved() {
+                    match game.step(dir) {
+                        Ok(StepOutcome::Blocked) => message = "blocked".to_string(),
+                        Ok(_) => (),
+                        Err(err) => message = err.to_string(),
+                    }
+                }
+            }
+            Key::Undo => {
+                if !game.undo() {
+                    message = "nothing to undo".to_string();
+                }
+            }
+            Key::Redo => {
+                if !game.redo() {
+                    message = "nothing to redo".to_string();
+                }
+            }
+            Key::Restart => game.restart(),
+            Key::Next | Key::Previous => {
+                let next = match key {
+                    Key::Next => current + 1,
+                    _ => current.wrapping_sub(1),
+                };
+                if next < levels.len() {
+                    current = next;
+                    game = Game::new(&collection[levels[current]]);
+                } else {
+                    message = "no more levels".to_string();
+                }
+            }
+            Key::Hint => {
+                let hint = game.hint(&hint_config);
+                message = format!("hint: {}", hint);
+                if let Hint::Deadlocked(deadlock) = &hint {
+                    message = format!("{}\n\n{}", message, game.view_deadlock(deadlock));
+                }
+            }
+            Key::Quit => break,
+        }
+        draw(&mut out, collection, levels[current], &game, &message)?;
+    }
+
+    drop(terminal);
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Returns `moves` in LURD notation.
+fn lurd(moves: &[Move]) -> String {
+    moves.iter().map(|m| m.dir.to_lurd(m.push)).collect()
+}
+
+/// Clears the screen and draws the level being played.
+fn draw<W: Write>(
+    out: &mut W,
+    collection: &QuestionCollection,
+    level: usize,
+    game: &Game,
+    message: &str,
+) -> io::Result<()> {
+    let title = match collection[level].title() {
+        Some(title) => format!("Level {} of {}: {}", level + 1, collection.len(), title),
+        None => format!("Level {} of {}", level + 1, collection.len()),
+    };
+    let status = if game.is_solved() {
+        format!(
+            "Solved in {} moves, {} pushes! Press n for the next level.\n{}",
+            game.move_count(),
+            game.push_count(),
+            lurd(game.history())
+        )
+    } else {
+        format!(
+            "moves: {}   pushes: {}",
+            game.move_count(),
+            game.push_count()
+        )
+    };
+
+    write!(out, "\x1b[H\x1b[2J")?;
+    writeln!(
+        out,
+        "{}\n\n{}\n\n{}\n{}\n\n{}",
+        title, game, status, message, KEYS
+    )?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_keys() {
+        assert_eq!(key_for(b'U'), Some(Key::Move(Dir::North)));
+        assert_eq!(key_for(b'l'), Some(Key::Move(Dir::West)));
+        assert_eq!(key_for(b'z'), Some(Key::Undo));
+        assert_eq!(key_for(0x03), Some(Key::Quit));
+        assert_eq!(key_for(b'?'), None);
+
+        let bytes = b"r\x1b[A\x1bOD?\x1b[Zy\x1bz\x1bq" as &[u8];
+        let keys = Keys {
+            bytes: bytes.bytes(),
+        };
+        assert_eq!(
+            keys.collect::<io::Result<Vec<_>>>().unwrap(),
+            vec![
+                Key::Move(Dir::East),
+                Key::Move(Dir::North),
+                Key::Move(Dir::West),
+                Key::Redo,
+                Key::Undo,
+                Key::Quit
+            ]
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use crate::question::QuestionCollection;
+
+/// Parses a list of 1-based level numbers and ranges, such as `3`, `2-5`, `7-` or
+/// `1,4,9-12`, into 0-based indices into `collection`, in the order given.
+///
+/// Fails on anything that isn't a level of `collection`, rather than skipping it.
+pub fn parse_levels(s: &str, collection: &QuestionCollection) -> Result<Vec<usize>, String> {
+    let len = collection.len();
+    let number = |part: &str| -> Result<usize, String> {
+        let n = part
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid level number {:?}", part))?;
+        if n == 0 || n > len {
+            return Err(format!(
+                "level {} is out of range, the collection has {} level{}",
+                n,
+                len,
+                if len == 1 { "" } else { "s" }
+            ));
+        }
+        Ok(n - 1)
+    };
+
+    let mut levels = vec![];
+    for part in s.split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let first = if first.trim().is_empty() {
+                    0
+                } else {
+                    number(first)?
+                };
+                let last = if last.trim().is_empty() {
+                    len.checked_sub(1).ok_or("the collection has no levels")?
+                } else {
+                    number(last)?
+                };
+                if first > last {
+                    return Err(format!("level range {:?} is backwards", part));
+                }
+                levels.extend(first..=last);
+            }
+            None => levels.push(number(part)?),
+        }
+    }
+    Ok(levels)
+}
+
+/// Returns the levels whose title is `title`, ignoring case, or failing that, the
+/// levels whose title contains it.
+pub fn find_titles(title: &str, collection: &QuestionCollection) -> Result<Vec<usize>, String> {
+    let lower = title.to_lowercase();
+    let titles = collection
+        .iter()
+        .map(|question| question.title().unwrap_or_default().to_lowercase())
+        .collect::<Vec<_>>();
+
+    let exact = (0..titles.len())
+        .filter(|&idx| titles[idx] == lower)
+        .collect::<Vec<_>>();
+    if !exact.is_empty() {
+        return Ok(exact);
+    }
+
+    let partial = (0..titles.len())
+        .filter(|&idx| titles[idx].contains(&lower))
+        .collect::<Vec<_>>();
+    if partial.is_empty() {
+        Err(format!("no level has a title matching {:?}", title))
+    } else {
+        Ok(partial)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn level_selection() {
+        let collection = QuestionCollection::from_str(include_str!("../../original.txt")).unwrap();
+
+        assert_eq!(parse_levels("3", &collection), Ok(vec![2]));
+        assert_eq!(parse_levels("1,4-6", &collection), Ok(vec![0, 3, 4, 5]));
+        assert_eq!(parse_levels("49-", &collection), Ok(vec![48, 49]));
+        assert!(parse_levels("0", &collection).is_err());
+        assert!(parse_levels("51", &collection).is_err());
+        assert!(parse_levels("6-4", &collection).is_err());
+        assert!(parse_levels("x", &collection).is_err());
+
+        assert_eq!(find_titles("level 5", &collection), Ok(vec![4]));
+        assert_eq!(find_titles("Level 4", &collection), Ok(vec![3]));
+        assert_eq!(find_titles("vel 4", &collection).unwrap().len(), 11);
+        assert!(find_titles("no such level", &collection).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! Making new levels by playing backwards from a solved position.
+//!
+//! Every box starts on a target and is pulled away from it, so the levels made are
+//! solvable by construction, and the pulls played forwards as pushes are a solution.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+
+use crate::question::{Position, Question, Square};
+use crate::solve::board::Board;
+use crate::solve::bounds::LowerBound;
+use crate::solve::puller::Puller;
+use crate::solve::{self, Algorithm, Game, SolverConfig, StepOutcome};
+
+mod layout;
+mod quality;
+mod rng;
+
+pub use layout::{layout, LayoutStyle};
+pub use quality::Interest;
+pub use rng::Rng;
+
+/// How many positions to search for a push optimal solution to a generated level,
+/// which judging how interesting it is needs.
+const SOLVE_NODES: usize = 20_000;
+
+#[derive(Debug, Clone)]
+pub struct GenerateConfig {
+    /// The size of the level, including its outer wall.
+    pub width: usize,
+    pub height: usize,
+    pub boxes: usize,
+    pub layout: LayoutStyle,
+    /// Only keep levels whose solution takes at least this many pushes. The solution is
+    /// push optimal when `min_interest` is above zero, so those levels need that many.
+    pub min_pushes: usize,
+    /// Only keep levels with at least this `Interest::score`. Zero keeps every level.
+    pub min_interest: f64,
+    /// How many pulls to make from the solved position.
+    pub pulls: usize,
+    /// How many layouts to try before giving up.
+    pub attempts: usize,
+    pub seed: u64,
+}
+
+impl Default for GenerateConfig {
+    fn default() -> Self {
+        Self {
+            width: 8,
+            height: 8,
+            boxes: 3,
+            layout: LayoutStyle::default(),
+            min_pushes: 10,
+            min_interest: 3.0,
+            pulls: 300,
+            attempts: 100,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerateError {
+    /// There isn't room inside the outer wall for the boxes and the player.
+    TooSmall,
+    /// None of the layouts tried gave a level deep or interesting enough.
+    GaveUp { attempts: usize },
+}
+
+impl Error for GenerateError {}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooSmall => write!(f, "the level is too small for that many boxes"),
+            Self::GaveUp { attempts } => {
+                write!(f, "no level good enough was found in {} attempts", attempts)
+            }
+        }
+    }
+}
+
+/// Makes a level from `config.seed`, along with a solution. The same config always
+/// gives the same level.
+pub fn generate(config: &GenerateConfig) -> Result<Question, GenerateError> {
+    let inside = config.width.saturating_sub(2) * config.height.saturating_sub(2);
+    if config.boxes == 0 || inside <= config.boxes {
+        return Err(GenerateError::TooSmall);
+    }
+
+    let mut rng = Rng::new(config.seed);
+    for _ in 0..config.attempts {
+        let Some(grid) = layout(config.width, config.height, config.layout, &mut rng) else {
+            continue;
+        };
+        let Some((mut question, mut solution)) = reverse_play(grid, config, &mut rng) else {
+            continue;
+        };
+        // Checked before solving too, as the optimal solution has no more pushes.
+        if solution.push_count() < config.min_pushes {
+            continue;
+        }
+        if config.min_interest > 0.0 {
+            // The pulls are a roundabout way to solve the level, which would make it
+            // look more interesting than it is.
+            let solver = SolverConfig {
+                algorithm: Algorithm::AStar,
+                max_nodes: Some(SOLVE_NODES),
+                ..SolverConfig::default()
+            };
+            let Some(optimal) = solve::solve_puzzle(&question, &solver).solution else {
+                continue;
+            };
+            let Ok(interest) = Interest::assess(&question, &optimal) else {
+                continue;
+            };
+            if interest.score < config.min_interest {
+                continue;
+            }
+            if optimal.push_count() < config.min_pushes {
+                continue;
+            }
+            solution = optimal;
+        }
+        question.add_solution((&solution).into());
+        question.set_metadata("Title", format!("Generated {}", config.seed));
+        question.set_metadata("Seed", config.seed.to_string());
+        return Ok(question);
+    }
+    Err(GenerateError::GaveUp {
+        attempts: config.attempts,
+    })
+}
+
+/// Returns true if every floor square can be walked to from every other.
+fn is_connected(grid: &[Vec<Square>]) -> bool {
+    let floor = floor_squares(grid);
+    let Some(&start) = floor.first() else {
+        return false;
+    };
+
+    let mut seen = HashSet::from([start]);
+    let mut bag = vec![start];
+    while let Some(pos) = bag.pop() {
+        for dir in solve::Dir::iter() {
+            if let Some(next) = pos.neighbour(dir) {
+                let floor = grid
+                    .get(next.y())
+                    .and_then(|row| row.get(next.x()))
+                    .is_some_and(|sq| *sq == Square::Space);
+                if floor && seen.insert(next) {
+                    bag.push(next);
+                }
+            }
+        }
+    }
+    seen.len() == floor.len()
+}
+
+fn floor_squares(grid: &[Vec<Square>]) -> Vec<Position> {
+    grid.iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, sq)| **sq == Square::Space)
+                .map(move |(x, _)| Position::new(x, y))
+        })
+        .collect()
+}
+
+/// Puts the boxes on random targets in `grid` and pulls them about at random. Returns
+/// the position on the way that needs the most pushes, if it needs enough of them,
+/// and the pulls that led there played as pushes.
+fn reverse_play(
+    grid: Vec<Vec<Square>>,
+    config: &GenerateConfig,
+    rng: &mut Rng,
+) -> Option<(Question, solve::Solution)> {
+    let mut floor = floor_squares(&grid);
+    if floor.len() <= config.boxes {
+        return None;
+    }
+    rng.shuffle(&mut floor);
+    let targets = floor[..config.boxes]
+        .iter()
+        .copied()
+        .collect::<HashSet<_>>();
+    let solved = Question::from_parts(
+        grid.clone(),
+        targets.clone(),
+        targets.clone(),
+        floor[config.boxes],
+    )
+    .ok()?;
+
+    let (board, mut player, mut boxes) = solve::create_board(&solved);
+    let puller = Puller::new(board.clone());
+    let bound = LowerBound::new(&board);
+
+    // The pulls made, and the deepest position seen with how many pulls it took.
+    let mut pulls = vec![];
+    let mut deepest = (0, 0, player, boxes.clone());
+    for _ in 0..config.pulls {
+        // Pulls that take a box further from the targets are preferred, half the time,
+        // so that the boxes don't just wander about near them.
+        let pulls_here = puller.find_pulls(player, &boxes);
+        let further = pulls_here
+            .iter()
+            .copied()
+            .filter(|&(pos, dir)| {
+                let from = board.step(pos, dir.opposite(), 1).unwrap();
+                bound.box_distance(pos) > bound.box_distance(from)
+            })
+            .collect::<Vec<_>>();
+        let choice = if !further.is_empty() && rng.chance(0.5) {
+            rng.choose(&further)
+        } else {
+            rng.choose(&pulls_here)
+        };
+        let Some(&(pos, dir)) = choice else {
+            break;
+        };
+        let from = board.step(pos, dir.opposite(), 1).unwrap();
+        boxes.remove(&from);
+        boxes.insert(pos);
+        player = board.step(pos, dir, 1).unwrap();
+        pulls.push((pos, dir));
+
+        let depth = bound.estimate(boxes.iter().copied()).unwrap_or(0);
+        if depth >= deepest.0 {
+            deepest = (depth, pulls.len(), player, boxes.clone());
+        }
+    }
+
+    let (depth, len, player, boxes) = deepest;
+    if depth == 0 {
+        // Every box is still on a target.
+        return None;
+    }
+
+    let position = |pos: usize| Position::from_usize(pos, board.width);
+    let question = Question::from_parts(
+        grid,
+        boxes.into_iter().map(position).collect(),
+        targets,
+        position(player),
+    )
+    .ok()?;
+    let solution = replay_pulls(&question, &board, &pulls[..len]);
+    Some((question, solution))
+}
+
+/// Undoes `pulls`, last first, by walking to each box and pushing it back.
+fn replay_pulls(
+    question: &Question,
+    board: &Board,
+    pulls: &[(usize, solve::Dir)],
+) -> solve::Solution {
+    let mut game = Game::new(question);
+    for &(pos, dir) in pulls.iter().rev() {
+        let walked = game.walk_to(board.step(pos, dir, 1).unwrap());
+        let pushed = game.step(dir.opposite());
+        assert!(walked && pushed == Ok(StepOutcome::Pushed));
+    }
+    assert!(game.is_solved(), "undoing the pulls didn't solve the level");
+    game.solution()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_levels_are_solvable() {
+        for seed in 0..5 {
+            let config = GenerateConfig {
+                seed,
+                ..GenerateConfig::default()
+            };
+            let question = generate(&config).unwrap();
+            assert_eq!(question.boxes().len(), config.boxes);
+
+            let solution = question.solutions()[0]
+                .lurd
+                .parse::<solve::Solution>()
+                .unwrap();
+            assert_eq!(solution.verify(&question), Ok(()));
+            assert!(solution.push_count() >= config.min_pushes);
+            assert_eq!(generate(&config).unwrap(), question);
+        }
+
+        let config = GenerateConfig {
+            width: 3,
+            height: 3,
+            ..GenerateConfig::default()
+        };
+        assert_eq!(generate(&config), Err(GenerateError::TooSmall));
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use super::Rng;
+use crate::question::{ParseError, Position, Square};
+use crate::solve::Dir;
+
+/// How the walls and floor of a level are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LayoutStyle {
+    /// Blocks of 3x3 squares, each a randomly turned copy of one of `TEMPLATES`.
+    #[default]
+    Templates,
+    /// Floor carved out by a walk in random directions.
+    RandomWalk,
+}
+
+impl LayoutStyle {
+    pub fn names() -> &'static [&'static str] {
+        &["templates", "walk"]
+    }
+}
+
+impl FromStr for LayoutStyle {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "templates" => Ok(Self::Templates),
+            "walk" => Ok(Self::RandomWalk),
+            _ => Err(ParseError::Text(format!(
+                "unknown layout {:?}, expected one of {}",
+                s,
+                Self::names().join(", ")
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for LayoutStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Templates => "templates",
+            Self::RandomWalk => "walk",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The pieces rooms are made from, row by row. They are turned and mirrored at random.
+const TEMPLATES: [&str; 12] = [
+    "         ",
+    "         ",
+    "#        ",
+    "##       ",
+    "###      ",
+    "#  #     ",
+    " #       ",
+    "# #      ",
+    "    #    ",
+    "## #     ",
+    "###   ###",
+    "##  #    ",
+];
+
+/// How much of the inside of a level a random walk carves out.
+const WALK_FLOOR: f64 = 0.5;
+
+/// Lays out a level `width` by `height` squares, outer wall included, in `style`.
+///
+/// Returns `None` if the layout turned out unusable: with floor that can't be reached
+/// from the rest, or with too little floor. Dead ends, which a box can never be pushed
+/// out of, are filled in with walls.
+pub fn layout(
+    width: usize,
+    height: usize,
+    style: LayoutStyle,
+    rng: &mut Rng,
+) -> Option<Vec<Vec<Square>>> {
+    if width < 3 || height < 3 {
+        return None;
+    }
+    let mut grid = vec![vec![Square::Wall; width]; height];
+    match style {
+        LayoutStyle::Templates => place_templates(&mut grid, rng),
+        LayoutStyle::RandomWalk => carve_walk(&mut grid, rng),
+    }
+    fill_dead_ends(&mut grid);
+
+    let floor = super::floor_squares(&grid).len();
+    let inside = (width - 2) * (height - 2);
+    (floor * 4 >= inside && super::is_connected(&grid)).then_some(grid)
+}
+
+/// Fills the inside of `grid` with 3x3 templates. When the inside isn't a multiple of 3
+/// across, the templates on the right or bottom are cut short.
+fn place_templates(grid: &mut [Vec<Square>], rng: &mut Rng) {
+    let (width, height) = (grid[0].len(), grid.len());
+    for y0 in (1..height - 1).step_by(3) {
+        for x0 in (1..width - 1).step_by(3) {
+            let template = TEMPLATES[rng.below(TEMPLATES.len())].as_bytes();
+            let (turns, mirror) = (rng.below(4), rng.chance(0.5));
+            for dy in 0..3 {
+                for dx in 0..3 {
+                    let (x, y) = (x0 + dx, y0 + dy);
+                    if x + 1 >= width || y + 1 >= height {
+                        continue;
+                    }
+                    let (mut tx, mut ty) = (dx, dy);
+                    if mirror {
+                        tx = 2 - tx;
+                    }
+                    for _ in 0..turns {
+                        (tx, ty) = (2 - ty, tx);
+                    }
+                    if template[ty * 3 + tx] == b' ' {
+                        grid[y][x] = Square::Space;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Carves floor out of the inside of `grid` by walking about from the middle.
+fn carve_walk(grid: &mut [Vec<Square>], rng: &mut Rng) {
+    let (width, height) = (grid[0].len(), grid.len());
+    let target = (((width - 2) * (height - 2)) as f64 * WALK_FLOOR).ceil() as usize;
+    let dirs = Dir::iter().collect::<Vec<_>>();
+
+    let mut pos = Position::new(width / 2, height / 2);
+    let mut carved = HashSet::new();
+    // A walk that goes on too long without finding new squares gives up.
+    for _ in 0..target * 50 {
+        grid[pos.y()][pos.x()] = Square::Space;
+        carved.insert(pos);
+        if carved.len() >= target {
+            break;
+        }
+
+        let dir = *rng.choose(&dirs).unwrap();
+        let inside = |next: &Position| {
+            (1..width - 1).contains(&next.x()) && (1..height - 1).contains(&next.y())
+        };
+        if let Some(next) = pos.neighbour(dir).filter(inside) {
+            pos = next;
+        }
+    }
+}
+
+/// Turns floor with walls on three or four sides into wall, until there is none left.
+fn fill_dead_ends(grid: &mut [Vec<Square>]) {
+    let (width, height) = (grid[0].len(), grid.len());
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                if grid[y][x] != Square::Space {
+                    continue;
+                }
+                let walls = [(x, y - 1), (x + 1, y), (x, y + 1), (x - 1, y)]
+                    .iter()
+                    .filter(|&&(x, y)| grid[y][x] == Square::Wall)
+                    .count();
+                if walls >= 3 {
+                    grid[y][x] = Square::Wall;
+                    changed = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layouts() {
+        for style in [LayoutStyle::Templates, LayoutStyle::RandomWalk] {
+            let mut made = 0;
+            for seed in 0..20 {
+                let Some(grid) = layout(11, 9, style, &mut Rng::new(seed)) else {
+                    continue;
+                };
+                made += 1;
+                assert_eq!(
+                    Some(&grid),
+                    layout(11, 9, style, &mut Rng::new(seed)).as_ref()
+                );
+                assert_eq!((grid[0].len(), grid.len()), (11, 9));
+                assert!(grid[0].iter().all(|sq| *sq == Square::Wall));
+                assert!(super::super::is_connected(&grid));
+
+                let mut filled = grid.clone();
+                fill_dead_ends(&mut filled);
+                assert_eq!(filled, grid);
+            }
+            assert!(
+                made >= 10,
+                "only {} of 20 {} layouts were usable",
+                made,
+                style
+            );
+        }
+    }
+}
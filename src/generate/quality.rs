@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use crate::question::{Position, Question};
+use crate::solve::{ReplayError, Solution};
+
+/// How interesting a level is to play, judged along a solution to it.
+///
+/// Levels where every box is simply pushed onto the nearest target, or where some box
+/// is never touched, are boring however long they take. Otherwise a level is more
+/// interesting the more pushes each box needs and the more the boxes get in each
+/// other's way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interest {
+    pub pushes: usize,
+    /// The number of boxes the solution never pushes.
+    pub idle_boxes: usize,
+    /// True if each box is pushed to its nearest target without a single push wasted.
+    pub straight: bool,
+    /// How many times a box is pushed again after other boxes were pushed in between,
+    /// which is a sign of boxes having to make way for each other.
+    pub interactions: usize,
+    /// Zero for levels that are rejected outright, otherwise pushes per box plus a
+    /// bonus for each interaction.
+    pub score: f64,
+}
+
+impl Interest {
+    /// Judges `question` along `solution`, which should be as short as can be found: a
+    /// roundabout solution makes a level look more interesting than it is.
+    pub fn assess(question: &Question, solution: &Solution) -> Result<Self, ReplayError> {
+        solution.verify(question)?;
+
+        // Each box, by where it started, with where it is now, how many times it was
+        // pushed, and the number of pushes made by the time of its last push.
+        let mut boxes = question
+            .boxes()
+            .iter()
+            .map(|&pos| (pos, (pos, 0, None)))
+            .collect::<HashMap<_, _>>();
+        let mut player = question.start();
+        let (mut pushes, mut interactions) = (0, 0);
+        for m in solution.moves() {
+            let next = player.neighbour(m.dir).unwrap();
+            if m.push {
+                let to = next.neighbour(m.dir).unwrap();
+                let (_, (pos, count, last)) = boxes
+                    .iter_mut()
+                    .find(|(_, (pos, _, _))| *pos == next)
+                    .unwrap();
+                // Another box was pushed since this one last was.
+                if last.is_some_and(|last| last != pushes) {
+                    interactions += 1;
+                }
+                *pos = to;
+                *count += 1;
+                pushes += 1;
+                *last = Some(pushes);
+            }
+            player = next;
+        }
+
+        let idle_boxes = boxes.values().filter(|(_, count, _)| *count == 0).count();
+        let straight = boxes.iter().all(|(start, &(_, count, _))| {
+            question
+                .targets()
+                .iter()
+                .map(|target| distance(*start, *target))
+                .min()
+                == Some(count)
+        });
+        let score = if idle_boxes > 0 || straight {
+            0.0
+        } else {
+            pushes as f64 / boxes.len() as f64 + interactions as f64 * 3.0
+        };
+
+        Ok(Self {
+            pushes,
+            idle_boxes,
+            straight,
+            interactions,
+            score,
+        })
+    }
+}
+
+fn distance(a: Position, b: Position) -> usize {
+    a.x().abs_diff(b.x()) + a.y().abs_diff(b.y())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn interest() {
+        let question = Question::from_str("#######\n#     #\n#@$  .#\n#  $ .#\n#######").unwrap();
+        let straight = Interest::assess(&question, &Solution::from_str("RRRlldRR").unwrap());
+        let straight = straight.unwrap();
+        assert!(straight.straight);
+        assert_eq!((straight.idle_boxes, straight.score), (0, 0.0));
+
+        // Both boxes still go straight to their targets, with a push of the second box
+        // in between pushes of the first.
+        let question = Question::from_str("#######\n#     #\n#@$  .#\n#   $.#\n#######").unwrap();
+        let solution = Solution::from_str("RdrRlluRR").unwrap();
+        let interest = Interest::assess(&question, &solution).unwrap();
+        assert_eq!((interest.pushes, interest.interactions), (4, 1));
+        assert!(interest.straight);
+
+        // The box has to go round the wall.
+        let question = Question::from_str("#######\n#     #\n#@ $  #\n#  #  #\n#  .  #\n#######");
+        let solution = Solution::from_str("rRurDDrdL").unwrap();
+        let interest = Interest::assess(&question.unwrap(), &solution).unwrap();
+        assert!(!interest.straight);
+        assert_eq!(interest.score, 4.0);
+
+        let question = Question::from_str("######\n#@$ .#\n#   *#\n######").unwrap();
+        let interest = Interest::assess(&question, &Solution::from_str("RR").unwrap());
+        assert_eq!(interest.unwrap().idle_boxes, 1);
+    }
+}
@@ -0,0 +1,66 @@
+//! Just enough JSON writing for the structured output of the command line.
+
+use std::fmt::{self, Display, Write};
+
+/// Returns `s` as a quoted JSON string.
+pub(crate) fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A JSON object, with its fields kept in the order they were added.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Object {
+    fields: Vec<(String, String)>,
+}
+
+impl Object {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn string(self, key: &str, value: &str) -> Self {
+        self.raw(key, string(value))
+    }
+
+    /// Adds a string field, or `null` if `value` is `None`.
+    pub fn optional(self, key: &str, value: Option<&str>) -> Self {
+        self.raw(key, value.map_or("null".to_string(), string))
+    }
+
+    pub fn number<T: Display>(self, key: &str, value: T) -> Self {
+        self.raw(key, value.to_string())
+    }
+
+    /// Adds a field whose value is already written as JSON.
+    pub fn raw<T: Into<String>>(mut self, key: &str, json: T) -> Self {
+        self.fields.push((key.to_string(), json.into()));
+        self
+    }
+}
+
+impl Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+        for (idx, (key, value)) in self.fields.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}:{}", string(key), value)?;
+        }
+        write!(f, "}}")
+    }
+}
@@ -1,12 +1,8 @@
+pub mod cli;
+pub mod generate;
+mod json;
 pub mod question;
 pub mod reader;
+pub mod render;
 pub mod solve;
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        let result = 2 + 2;
-        assert_eq!(result, 4);
-    }
-}
+pub mod stats;
@@ -2,7 +2,17 @@ use std::collections::HashSet;
 use std::error::Error;
 use std::fmt;
 use std::ops::Index;
-use std::str::FromStr;
+
+use crate::solve::Dir;
+
+mod canonical;
+mod simplify;
+mod transform;
+mod xsb;
+
+pub use simplify::Simplified;
+pub use transform::Symmetry;
+pub(crate) use xsb::{is_board_line, parse_metadata_entry, write_metadata};
 
 #[derive(Debug)]
 pub enum ErrorKind {}
@@ -27,14 +37,14 @@ impl Error for ParseError {
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "Something went wrong... ParseError... In DISPLAY (change later)"
-        )
+        match self {
+            Self::Text(text) => write!(f, "{}", text),
+            Self::Kind(kind) => write!(f, "{:?}", kind),
+        }
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Square {
     Space,
     Wall,
@@ -52,41 +62,50 @@ pub enum Square {
 //     }
 // }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct QuestionCollection {
     questions: Vec<Question>,
+    /// The `Key: value` lines that describe the whole collection, in order.
+    metadata: Vec<(String, String)>,
 }
 
 impl QuestionCollection {
-    pub fn len(&self) -> usize {
-        self.questions.len()
+    pub fn new(questions: Vec<Question>) -> Self {
+        Self {
+            questions,
+            metadata: vec![],
+        }
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    pub fn push(&mut self, question: Question) {
+        self.questions.push(question);
     }
-}
 
-impl FromStr for QuestionCollection {
-    type Err = ParseError;
+    pub fn metadata(&self) -> &[(String, String)] {
+        &self.metadata
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut lines = s.lines().peekable();
-        let mut puzzles = vec![];
-        while lines.peek().is_some() {
-            let puzzle_str = lines
-                .by_ref()
-                .take_while(|line| !line.chars().all(char::is_whitespace))
-                .collect::<Vec<_>>()
-                .join("\n");
+    /// Returns the value of the first metadata entry named `key`, ignoring case.
+    pub fn get_metadata(&self, key: &str) -> Option<&str> {
+        find_metadata(&self.metadata, key)
+    }
 
-            // May need to do something for cases if there are multiple blank lines separating puzzles
-            let puzzle = Question::from_str(&puzzle_str).map_err(|err| {
-                ParseError::new(format!("error on grid {}, {:?}", puzzles.len() + 1, err))
-            })?;
-            puzzles.push(puzzle);
-        }
+    /// Sets the value of the metadata entry named `key`, adding it to the end if
+    /// there isn't one already.
+    pub fn set_metadata<K: AsRef<str>, V: Into<String>>(&mut self, key: K, value: V) {
+        set_metadata(&mut self.metadata, key.as_ref(), value.into());
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.get_metadata("Title")
+    }
+
+    pub fn len(&self) -> usize {
+        self.questions.len()
+    }
 
-        Ok(Self { questions: puzzles })
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 }
 
@@ -98,6 +117,11 @@ impl QuestionCollection {
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Question> {
         self.questions.iter_mut()
     }
+
+    /// Moves all the levels of `other` onto the end of this collection.
+    pub fn append(&mut self, other: QuestionCollection) {
+        self.questions.extend(other.questions);
+    }
 }
 
 impl IntoIterator for QuestionCollection {
@@ -117,10 +141,41 @@ impl Index<usize> for QuestionCollection {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Position(usize, usize);
 
 impl Position {
+    pub fn new(x: usize, y: usize) -> Self {
+        Self(x, y)
+    }
+
+    /// The column, counting from 0 on the left.
+    pub fn x(&self) -> usize {
+        self.0
+    }
+
+    /// The row, counting from 0 at the top.
+    pub fn y(&self) -> usize {
+        self.1
+    }
+
+    /// Returns the position next to this one in `dir`, or `None` if that would be
+    /// above or left of the grid.
+    pub fn neighbour(&self, dir: Dir) -> Option<Self> {
+        let Self(x, y) = *self;
+        match dir {
+            Dir::North => y.checked_sub(1).map(|y| Self(x, y)),
+            Dir::East => Some(Self(x + 1, y)),
+            Dir::South => Some(Self(x, y + 1)),
+            Dir::West => x.checked_sub(1).map(|x| Self(x, y)),
+        }
+    }
+
+    /// Converts the 1D position `pos` back, for a grid `width` squares wide.
+    pub fn from_usize(pos: usize, width: usize) -> Self {
+        Self(pos % width, pos / width)
+    }
+
     /// Converts the position into a 1D `usize`. The output position
     /// depends on the `width`.
     pub fn to_usize(&self, width: usize) -> usize {
@@ -129,7 +184,7 @@ impl Position {
 }
 
 #[allow(dead_code)]
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Question {
     width: usize,
     height: usize,
@@ -137,9 +192,99 @@ pub struct Question {
     boxes: HashSet<Position>,
     targets: HashSet<Position>,
     start: Position,
+    /// The `Key: value` lines that came with the level, in order.
+    metadata: Vec<(String, String)>,
+    solutions: Vec<SavedSolution>,
+    source: Option<LevelSource>,
+}
+
+/// Where a level was loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelSource {
+    /// The path of the file, or `-` for stdin.
+    pub path: String,
+    /// The position of the level within the file, starting from 0.
+    pub index: usize,
+}
+
+impl fmt::Display for LevelSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.path, self.index + 1)
+    }
+}
+
+/// A solution or snapshot stored alongside a level, in LURD notation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedSolution {
+    pub lurd: String,
+    /// Snapshots are unfinished attempts rather than full solutions.
+    pub snapshot: bool,
+}
+
+impl SavedSolution {
+    pub fn move_count(&self) -> usize {
+        self.lurd.chars().filter(char::is_ascii_alphabetic).count()
+    }
+
+    pub fn push_count(&self) -> usize {
+        self.lurd.chars().filter(char::is_ascii_uppercase).count()
+    }
 }
 
 impl Question {
+    /// Builds a level from its grid, which must be rectangular, and the positions of
+    /// the boxes, targets and player, which must all be on floor.
+    pub fn from_parts(
+        grid: Vec<Vec<Square>>,
+        boxes: HashSet<Position>,
+        targets: HashSet<Position>,
+        start: Position,
+    ) -> Result<Self, ParseError> {
+        let (width, height) = (grid.first().map_or(0, Vec::len), grid.len());
+        if width == 0 || grid.iter().any(|row| row.len() != width) {
+            return Err(ParseError::new(
+                "the grid must be rectangular and not empty",
+            ));
+        }
+        let on_floor =
+            |&Position(x, y): &Position| x < width && y < height && grid[y][x] == Square::Space;
+        if !boxes
+            .iter()
+            .chain(targets.iter())
+            .chain([&start])
+            .all(on_floor)
+        {
+            return Err(ParseError::new(
+                "the boxes, targets and player must all be on floor",
+            ));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            grid,
+            boxes,
+            targets,
+            start,
+            metadata: vec![],
+            solutions: vec![],
+            source: None,
+        })
+    }
+
+    /// Checks that the level can be played as it is: that it has a box for every target.
+    /// Parsing only checks that the grid makes sense.
+    pub fn validate(&self) -> Result<(), ParseError> {
+        if self.boxes.len() != self.targets.len() {
+            return Err(ParseError::new(format!(
+                "{} boxes but {} targets",
+                self.boxes.len(),
+                self.targets.len()
+            )));
+        }
+        Ok(())
+    }
+
     pub fn rows(&self) -> impl Iterator<Item = &Vec<Square>> {
         self.grid.iter()
     }
@@ -163,92 +308,121 @@ impl Question {
     pub fn start(&self) -> Position {
         self.start
     }
+
+    pub fn metadata(&self) -> &[(String, String)] {
+        &self.metadata
+    }
+
+    /// Returns the value of the first metadata entry named `key`, ignoring case.
+    pub fn get_metadata(&self, key: &str) -> Option<&str> {
+        find_metadata(&self.metadata, key)
+    }
+
+    /// Sets the value of the metadata entry named `key`, adding it to the end if
+    /// there isn't one already.
+    pub fn set_metadata<K: AsRef<str>, V: Into<String>>(&mut self, key: K, value: V) {
+        set_metadata(&mut self.metadata, key.as_ref(), value.into());
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.get_metadata("Title")
+    }
+
+    /// Returns the solutions and snapshots that were saved with the level.
+    pub fn solutions(&self) -> &[SavedSolution] {
+        &self.solutions
+    }
+
+    pub fn add_solution(&mut self, solution: SavedSolution) {
+        self.solutions.push(solution);
+    }
+
+    /// Returns the file the level was read from, if it was read from one.
+    pub fn source(&self) -> Option<&LevelSource> {
+        self.source.as_ref()
+    }
+
+    pub fn set_source(&mut self, source: LevelSource) {
+        self.source = Some(source);
+    }
+}
+
+fn find_metadata<'a>(metadata: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    metadata
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| v.as_str())
 }
 
-// TODO:
-// Player on goal square represented by '+'
+fn set_metadata(metadata: &mut Vec<(String, String)>, key: &str, value: String) {
+    match metadata
+        .iter_mut()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+    {
+        Some((_, v)) => *v = value,
+        None => metadata.push((key.to_string(), value)),
+    }
+}
+
+impl Index<Position> for Question {
+    type Output = Square;
 
-const WALL_CHAR: char = '#';
-const SPACE_CHAR: char = ' ';
-const BOX_CHAR: char = '$';
-const TARGET_CHAR: char = '.';
-const PLACED_CHAR: char = '*';
-const START_CHAR: char = '@';
+    fn index(&self, Position(x, y): Position) -> &Self::Output {
+        &self.grid[y][x]
+    }
+}
 
-impl FromStr for Question {
-    type Err = ParseError;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let rows = s
-            .lines()
-            .map(|row| row.chars().collect::<Vec<_>>())
-            .collect::<Vec<_>>();
+    #[test]
+    fn xsb_round_trip() {
+        let collection = QuestionCollection::from_str(include_str!("../original.txt")).unwrap();
+        assert_eq!(collection.len(), 50);
+        assert_eq!(collection[0].title(), Some("Level 1"));
 
-        let width = rows
-            .iter()
-            .map(|row| row.len())
-            .max()
-            .ok_or_else(|| ParseError::new("Puzzle was empty"))?;
-
-        let mut boxes = HashSet::new();
-        let mut targets = HashSet::new();
-        let mut start = None;
-
-        let mut grid = vec![];
-        for (y, row) in rows.iter().enumerate() {
-            let mut reached_wall = false;
-            let mut row_squares = Vec::with_capacity(width);
-            for (x, &c) in row.iter().enumerate() {
-                let square = match c {
-                    WALL_CHAR => {
-                        reached_wall = true;
-                        Square::Wall
-                    }
-                    SPACE_CHAR | TARGET_CHAR | BOX_CHAR | START_CHAR | PLACED_CHAR => {
-                        if !reached_wall {
-                            Square::Wall
-                        } else {
-                            let pos = Position(x, y);
-                            match c {
-                                TARGET_CHAR => {
-                                    targets.insert(pos);
-                                }
-                                BOX_CHAR => {
-                                    boxes.insert(pos);
-                                }
-                                PLACED_CHAR => {
-                                    targets.insert(pos);
-                                    boxes.insert(pos);
-                                }
-                                START_CHAR => {
-                                    match start {
-                                        Some(_) => {
-                                            return Err(ParseError::new("multiple start positions"))
-                                        }
-                                        None => start = Some(pos),
-                                    };
-                                }
-                                SPACE_CHAR => (),
-                                _ => unreachable!(),
-                            };
-                            Square::Space
-                        }
-                    }
-                    _ => return Err(ParseError::new("invalid text in grid")),
-                };
-                row_squares.push(square);
-            }
-            row_squares.resize(width, Square::Wall);
-            grid.push(row_squares);
+        for question in collection.iter() {
+            let text = question.to_string();
+            assert_eq!(&Question::from_str(&text).unwrap(), question);
+
+            let padded = question.pad(2).rotate_90();
+            assert_eq!(Question::from_str(&padded.to_string()).unwrap(), padded);
         }
+    }
 
-        Ok(Self {
-            width,
-            height: rows.len(),
-            grid,
-            boxes,
-            targets,
-            start: start.ok_or_else(|| ParseError::new("no start position"))?,
-        })
+    #[test]
+    fn keeps_comments() {
+        let text =
+            "Some levels I made\nAuthor: Me\n\n#####\n#@$.#\n#####\nTitle: One\nToo easy.\nMaybe.";
+        let collection = QuestionCollection::from_str(text).unwrap();
+        assert_eq!(
+            collection.get_metadata("Comment"),
+            Some("Some levels I made")
+        );
+        assert_eq!(
+            collection[0].get_metadata("Comment"),
+            Some("Too easy.\nMaybe.")
+        );
+        assert_eq!(collection[0].title(), Some("One"));
+
+        let text = collection.to_string();
+        assert_eq!(QuestionCollection::from_str(&text).unwrap(), collection);
+    }
+
+    #[test]
+    fn rle_round_trip() {
+        let question = Question::from_str("5#|#@$.#|5#").unwrap();
+        assert_eq!(question.to_string(), "#####\n#@$.#\n#####");
+        assert_eq!(question.to_rle(), "5#|#@$.#|5#");
+
+        let collection = QuestionCollection::from_str(include_str!("../original.txt")).unwrap();
+        let rle = collection.to_rle();
+        assert_eq!(QuestionCollection::from_str(&rle).unwrap(), collection);
+
+        let packed = rle.lines().filter(|line| line.contains('|'));
+        let packed = packed.collect::<Vec<_>>().join("\n");
+        assert_eq!(QuestionCollection::from_str(&packed).unwrap().len(), 50);
     }
 }
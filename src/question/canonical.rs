@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+
+use super::transform::Symmetry;
+use super::{Position, Question, QuestionCollection, Square};
+
+impl Question {
+    /// Returns the canonical form of the level.
+    ///
+    /// Floor the player can never reach is walled off, the outer frame is trimmed,
+    /// the player is moved to the top left square they can walk to, and the smallest
+    /// of the 8 rotations and reflections is picked. Two levels that only differ by
+    /// these have the same canonical form.
+    pub fn canonical(&self) -> Question {
+        let normalised = self.normalised();
+        Symmetry::iter()
+            .map(|symmetry| {
+                let question = normalised.transform(symmetry).with_player_top_left();
+                (question.canonical_key(), question)
+            })
+            .min_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, question)| question)
+            .unwrap()
+    }
+
+    /// Returns a stable hash of the canonical form of the level as a hex string.
+    pub fn fingerprint(&self) -> String {
+        format!(
+            "{:016x}",
+            fnv1a(self.canonical().canonical_key().as_bytes())
+        )
+    }
+
+    /// Walls off the floor the player can't reach, and trims the outer frame.
+    fn normalised(&self) -> Question {
+        let reachable = self.flood_fill(|pos| matches!(self[pos], Square::Space));
+
+        let mut question = self.clone();
+        for (y, row) in question.grid.iter_mut().enumerate() {
+            for (x, sq) in row.iter_mut().enumerate() {
+                let pos = Position(x, y);
+                if !reachable.contains(&pos)
+                    && !self.boxes.contains(&pos)
+                    && !self.targets.contains(&pos)
+                {
+                    *sq = Square::Wall;
+                }
+            }
+        }
+        question.trim()
+    }
+
+    /// Moves the player to the top left square they can reach without pushing a box.
+    fn with_player_top_left(mut self) -> Self {
+        let reachable =
+            self.flood_fill(|pos| matches!(self[pos], Square::Space) && !self.boxes.contains(&pos));
+        self.start = reachable
+            .into_iter()
+            .min_by_key(|&Position(x, y)| (y, x))
+            .unwrap();
+        self
+    }
+
+    /// Returns all the positions connected to the start through positions where `passable` is true.
+    fn flood_fill(&self, passable: impl Fn(Position) -> bool) -> HashSet<Position> {
+        let mut bag = vec![self.start];
+        let mut visited = HashSet::from([self.start]);
+
+        while let Some(Position(x, y)) = bag.pop() {
+            let neighbours = [
+                y.checked_sub(1).map(|y| Position(x, y)),
+                (x + 1 < self.width).then(|| Position(x + 1, y)),
+                (y + 1 < self.height).then(|| Position(x, y + 1)),
+                x.checked_sub(1).map(|x| Position(x, y)),
+            ];
+            for pos in neighbours.into_iter().flatten() {
+                if passable(pos) && visited.insert(pos) {
+                    bag.push(pos);
+                }
+            }
+        }
+
+        visited
+    }
+
+    fn canonical_key(&self) -> String {
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| self.char_at(Position(x, y)))
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl QuestionCollection {
+    /// Returns the indices of levels that are the same up to rotation, reflection and
+    /// padding, grouped together. Levels without a duplicate are left out.
+    pub fn duplicates(&self) -> Vec<Vec<usize>> {
+        let mut groups = HashMap::<String, Vec<usize>>::new();
+        let mut order = vec![];
+        for (idx, question) in self.questions.iter().enumerate() {
+            let fingerprint = question.fingerprint();
+            if !groups.contains_key(&fingerprint) {
+                order.push(fingerprint.clone());
+            }
+            groups.entry(fingerprint).or_default().push(idx);
+        }
+
+        order
+            .into_iter()
+            .filter_map(|fingerprint| groups.remove(&fingerprint))
+            .filter(|group| group.len() > 1)
+            .collect()
+    }
+
+    /// Removes every level that is a duplicate of an earlier one.
+    pub fn dedup(&mut self) {
+        let mut seen = HashSet::new();
+        self.questions
+            .retain(|question| seen.insert(question.fingerprint()));
+    }
+}
+
+/// 64 bit FNV-1a. Used instead of `DefaultHasher` so fingerprints stay the same
+/// between builds.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn fingerprint_ignores_orientation_and_padding() {
+        let question = Question::from_str("#####\n#@$.#\n# # #\n#   #\n#####").unwrap();
+        let moved = Question::from_str("  #####\n  # $.#\n  # # #\n  #@  #\n  #####").unwrap();
+        let other = Question::from_str("#####\n#@ $.#\n#   #\n#####").unwrap();
+
+        for symmetry in Symmetry::iter() {
+            let transformed = moved.transform(symmetry);
+            assert_eq!(question.fingerprint(), transformed.fingerprint());
+        }
+        assert_ne!(question.fingerprint(), other.fingerprint());
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+
+use super::{Position, Question, Square};
+use crate::solve::{self, Dir};
+
+/// A smaller level that plays the same as the one it was made from, as returned by
+/// `Question::simplify`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Simplified {
+    pub question: Question,
+    /// Where the top left corner of the simplified level is in the original.
+    pub offset: Position,
+    /// The number of floor squares that were turned into walls, not counting fixed boxes.
+    pub removed_floor: usize,
+    /// The number of boxes on targets that could never move, and were turned into walls.
+    pub fixed_boxes: usize,
+}
+
+impl Simplified {
+    /// Maps a position in the simplified level to the same square in the original.
+    pub fn original_position(&self, Position(x, y): Position) -> Position {
+        Position(x + self.offset.0, y + self.offset.1)
+    }
+
+    /// Maps a LURD solution of the simplified level to one of the original level.
+    ///
+    /// Moves are relative to the player, and simplifying only takes squares away, so
+    /// this is always the same solution. It is here so that code that simplifies
+    /// levels doesn't need to rely on that.
+    pub fn original_lurd(&self, lurd: &str) -> String {
+        lurd.to_string()
+    }
+}
+
+impl Question {
+    /// Returns an equivalent level with everything that doesn't matter to solving it
+    /// taken away: boxes on targets that can never be pushed become walls, as does
+    /// floor that no box can be pushed onto without deadlocking, unless the player
+    /// might need it to get about. The outer wall is then trimmed.
+    ///
+    /// Saved solutions aren't kept, since they may walk over the squares taken away.
+    pub fn simplify(&self) -> Simplified {
+        let mut grid = self.grid.clone();
+        let mut boxes = self.boxes.clone();
+        let mut targets = self.targets.clone();
+
+        // Boxes next to a wall, or another fixed box, across both ways can't be pushed.
+        let mut fixed_boxes = 0;
+        loop {
+            let fixed = boxes
+                .iter()
+                .filter(|pos| targets.contains(pos) && is_fixed(&grid, **pos))
+                .copied()
+                .collect::<Vec<_>>();
+            if fixed.is_empty() {
+                break;
+            }
+            for pos in fixed {
+                grid[pos.1][pos.0] = Square::Wall;
+                boxes.remove(&pos);
+                targets.remove(&pos);
+                fixed_boxes += 1;
+            }
+        }
+
+        let question = Question::from_parts(grid.clone(), boxes, targets, self.start)
+            .expect("fixed boxes were on floor");
+        let (board, start, _) = solve::create_board(&question);
+        let position = |pos: usize| Position::from_usize(pos, board.width);
+        let index = |Position(x, y): Position| y * board.width + x;
+
+        // The squares that can't go: those a box can usefully be on, the squares the
+        // player pushes from, and any with something on them.
+        let mut needed = question
+            .boxes
+            .iter()
+            .chain(question.targets.iter())
+            .map(|&pos| index(pos))
+            .chain([start])
+            .collect::<HashSet<_>>();
+        for pos in (0..board.grid.len()).filter(|&pos| board.square_at(pos).is_valid()) {
+            needed.insert(pos);
+            for dir in Dir::iter() {
+                let to = board.step(pos, dir, 1);
+                let from = board.step(pos, dir.opposite(), 1);
+                if let (Some(to), Some(from)) = (to, from) {
+                    if board.square_at(to).is_valid() && board.square_at(from).is_space() {
+                        needed.insert(from);
+                    }
+                }
+            }
+        }
+
+        // The floor the player can get to, less any dead ends that aren't needed, which
+        // can't be on the way from one square to another.
+        let mut floor = HashSet::from([start]);
+        let mut bag = vec![start];
+        while let Some(pos) = bag.pop() {
+            for next in board.borders(pos) {
+                if board.square_at(next).is_space() && floor.insert(next) {
+                    bag.push(next);
+                }
+            }
+        }
+        let mut bag = floor.iter().copied().collect::<Vec<_>>();
+        while let Some(pos) = bag.pop() {
+            let neighbours = board
+                .borders(pos)
+                .filter(|next| floor.contains(next))
+                .collect::<Vec<_>>();
+            if !needed.contains(&pos) && neighbours.len() <= 1 && floor.remove(&pos) {
+                bag.extend(neighbours);
+            }
+        }
+
+        let mut removed_floor = 0;
+        for (pos, sq) in grid.iter_mut().flatten().enumerate() {
+            if *sq == Square::Space && !floor.contains(&pos) && !needed.contains(&pos) {
+                *sq = Square::Wall;
+                removed_floor += 1;
+            }
+        }
+
+        let mut simplified =
+            Question::from_parts(grid, question.boxes, question.targets, self.start)
+                .expect("only empty floor was removed")
+                .trim();
+        simplified.metadata = self.metadata.clone();
+        simplified.source = self.source.clone();
+        let offset = Position(
+            position(start).0 - simplified.start.0,
+            position(start).1 - simplified.start.1,
+        );
+
+        Simplified {
+            question: simplified,
+            offset,
+            removed_floor,
+            fixed_boxes,
+        }
+    }
+}
+
+/// Returns true if a box at `pos` has a wall on at least one side across and one side
+/// up and down, so that it can't be pushed either way.
+fn is_fixed(grid: &[Vec<Square>], pos: Position) -> bool {
+    let wall = |dir: Dir| {
+        pos.neighbour(dir)
+            .and_then(|Position(x, y)| grid.get(y)?.get(x))
+            .is_none_or(|sq| *sq == Square::Wall)
+    };
+    (wall(Dir::West) || wall(Dir::East)) && (wall(Dir::North) || wall(Dir::South))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn simplify() {
+        let question = Question::from_str("#######\n#*    #\n# @$ .#\n##### #\n    ###").unwrap();
+        let simplified = question.simplify();
+        assert_eq!(simplified.fixed_boxes, 1);
+        // A box pushed into the dead end at the bottom could never come out, and the
+        // player has no reason to go there. The floor along the top stays, as the player
+        // may need it to get round the box.
+        assert_eq!(
+            simplified.question.to_string(),
+            " ######\n##    #\n# @$ .#\n#######"
+        );
+        assert_eq!(
+            (simplified.offset, simplified.removed_floor),
+            (Position(0, 0), 1)
+        );
+
+        let solution = solve::Solution::from_str("RR").unwrap();
+        assert_eq!(solution.verify(&simplified.question), Ok(()));
+        let original = simplified
+            .original_lurd(&solution.to_string())
+            .parse::<solve::Solution>();
+        assert_eq!(original.unwrap().verify(&question), Ok(()));
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashSet;
+
+use super::{Position, Question, SavedSolution, Square};
+
+/// One of the 8 rotations and reflections of a grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    Identity,
+    /// Rotated 90 degrees clockwise.
+    Rotate90,
+    Rotate180,
+    /// Rotated 270 degrees clockwise.
+    Rotate270,
+    /// Mirrored left to right.
+    MirrorHorizontal,
+    /// Mirrored top to bottom.
+    MirrorVertical,
+    /// Reflected in the leading diagonal.
+    Transpose,
+    /// Reflected in the other diagonal.
+    AntiTranspose,
+}
+
+impl Symmetry {
+    pub fn iter() -> impl Iterator<Item = Self> {
+        [
+            Self::Identity,
+            Self::Rotate90,
+            Self::Rotate180,
+            Self::Rotate270,
+            Self::MirrorHorizontal,
+            Self::MirrorVertical,
+            Self::Transpose,
+            Self::AntiTranspose,
+        ]
+        .into_iter()
+    }
+
+    /// Returns true if the symmetry swaps the width and height of the grid.
+    fn swaps_axes(&self) -> bool {
+        matches!(
+            self,
+            Self::Rotate90 | Self::Rotate270 | Self::Transpose | Self::AntiTranspose
+        )
+    }
+
+    /// Returns the symmetry that undoes this one.
+    pub fn inverse(&self) -> Self {
+        match self {
+            Self::Rotate90 => Self::Rotate270,
+            Self::Rotate270 => Self::Rotate90,
+            other => *other,
+        }
+    }
+
+    /// Maps a LURD character to the one that moves the same way in the transformed
+    /// level. Pushes stay uppercase. Any other character is returned unchanged.
+    pub fn transform_lurd_char(&self, c: char) -> char {
+        const LURD: [char; 4] = ['u', 'r', 'd', 'l'];
+        let idx = match LURD.iter().position(|&d| d == c.to_ascii_lowercase()) {
+            Some(idx) => idx,
+            None => return c,
+        };
+        // Indices are clockwise from up, so rotations are offsets and reflections
+        // are an offset after reversing the order.
+        let new_idx = match self {
+            Self::Identity => idx,
+            Self::Rotate90 => idx + 1,
+            Self::Rotate180 => idx + 2,
+            Self::Rotate270 => idx + 3,
+            Self::MirrorHorizontal => 4 - idx,
+            Self::MirrorVertical => 6 - idx,
+            Self::Transpose => 7 - idx,
+            Self::AntiTranspose => 5 - idx,
+        } % 4;
+        if c.is_ascii_uppercase() {
+            LURD[new_idx].to_ascii_uppercase()
+        } else {
+            LURD[new_idx]
+        }
+    }
+
+    /// Transforms a LURD solution so that it solves the transformed level.
+    pub fn transform_lurd(&self, lurd: &str) -> String {
+        lurd.chars().map(|c| self.transform_lurd_char(c)).collect()
+    }
+
+    /// Maps `pos` in a grid of size `width` x `height` to where it ends up after the transform.
+    fn map(&self, Position(x, y): Position, width: usize, height: usize) -> Position {
+        let (right, bottom) = (width - 1, height - 1);
+        match self {
+            Self::Identity => Position(x, y),
+            Self::Rotate90 => Position(bottom - y, x),
+            Self::Rotate180 => Position(right - x, bottom - y),
+            Self::Rotate270 => Position(y, right - x),
+            Self::MirrorHorizontal => Position(right - x, y),
+            Self::MirrorVertical => Position(x, bottom - y),
+            Self::Transpose => Position(y, x),
+            Self::AntiTranspose => Position(bottom - y, right - x),
+        }
+    }
+}
+
+impl Question {
+    /// Returns a copy of the level with `symmetry` applied to it. Saved solutions are
+    /// transformed to match, and others can be with `Symmetry::transform_lurd`.
+    pub fn transform(&self, symmetry: Symmetry) -> Question {
+        let (width, height) = if symmetry.swaps_axes() {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        let map = |pos: Position| symmetry.map(pos, self.width, self.height);
+        let map_set = |set: &HashSet<Position>| set.iter().map(|&pos| map(pos)).collect();
+
+        let mut grid = vec![vec![Square::Wall; width]; height];
+        for (y, row) in self.grid.iter().enumerate() {
+            for (x, sq) in row.iter().enumerate() {
+                let Position(nx, ny) = map(Position(x, y));
+                grid[ny][nx] = sq.clone();
+            }
+        }
+
+        Question {
+            width,
+            height,
+            grid,
+            boxes: map_set(&self.boxes),
+            targets: map_set(&self.targets),
+            start: map(self.start),
+            metadata: self.metadata.clone(),
+            solutions: self
+                .solutions
+                .iter()
+                .map(|solution| SavedSolution {
+                    lurd: symmetry.transform_lurd(&solution.lurd),
+                    snapshot: solution.snapshot,
+                })
+                .collect(),
+            source: self.source.clone(),
+        }
+    }
+
+    pub fn rotate_90(&self) -> Question {
+        self.transform(Symmetry::Rotate90)
+    }
+
+    pub fn rotate_180(&self) -> Question {
+        self.transform(Symmetry::Rotate180)
+    }
+
+    pub fn rotate_270(&self) -> Question {
+        self.transform(Symmetry::Rotate270)
+    }
+
+    pub fn mirror_horizontal(&self) -> Question {
+        self.transform(Symmetry::MirrorHorizontal)
+    }
+
+    pub fn mirror_vertical(&self) -> Question {
+        self.transform(Symmetry::MirrorVertical)
+    }
+
+    /// Returns a copy of the level with all the outer rows and columns that don't
+    /// contain any floor removed, leaving a single wall around the floor.
+    /// Solutions are unaffected.
+    pub fn trim(&self) -> Question {
+        let floor = self.grid.iter().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, sq)| matches!(sq, Square::Space))
+                .map(move |(x, _)| (x, y))
+        });
+        let (mut left, mut top, mut right, mut bottom) = (usize::MAX, usize::MAX, 0, 0);
+        for (x, y) in floor {
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x);
+            bottom = bottom.max(y);
+        }
+        if left == usize::MAX {
+            return self.clone();
+        }
+
+        let (left, top) = (left.saturating_sub(1), top.saturating_sub(1));
+        let right = (right + 1).min(self.width - 1);
+        let bottom = (bottom + 1).min(self.height - 1);
+
+        let shift = |set: &HashSet<Position>| {
+            set.iter()
+                .map(|&Position(x, y)| Position(x - left, y - top))
+                .collect()
+        };
+
+        Question {
+            width: right - left + 1,
+            height: bottom - top + 1,
+            grid: self.grid[top..=bottom]
+                .iter()
+                .map(|row| row[left..=right].to_vec())
+                .collect(),
+            boxes: shift(&self.boxes),
+            targets: shift(&self.targets),
+            start: Position(self.start.0 - left, self.start.1 - top),
+            metadata: self.metadata.clone(),
+            solutions: self.solutions.clone(),
+            source: self.source.clone(),
+        }
+    }
+}
+
+impl Question {
+    /// Returns a copy of the level surrounded by `amount` extra rows and columns of wall
+    /// on every side. Solutions are unaffected.
+    pub fn pad(&self, amount: usize) -> Question {
+        let width = self.width + 2 * amount;
+        let mut grid = vec![vec![Square::Wall; width]; amount];
+        for row in self.grid.iter() {
+            let mut new_row = vec![Square::Wall; amount];
+            new_row.extend(row.iter().cloned());
+            new_row.resize(width, Square::Wall);
+            grid.push(new_row);
+        }
+        grid.resize(self.height + 2 * amount, vec![Square::Wall; width]);
+
+        let shift = |set: &HashSet<Position>| {
+            set.iter()
+                .map(|&Position(x, y)| Position(x + amount, y + amount))
+                .collect()
+        };
+
+        Question {
+            width,
+            height: self.height + 2 * amount,
+            grid,
+            boxes: shift(&self.boxes),
+            targets: shift(&self.targets),
+            start: Position(self.start.0 + amount, self.start.1 + amount),
+            metadata: self.metadata.clone(),
+            solutions: self.solutions.clone(),
+            source: self.source.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn transforms_undo() {
+        let question = Question::from_str(LEVEL).unwrap();
+        let lurd = "RRurD";
+
+        for symmetry in Symmetry::iter() {
+            let transformed = question.transform(symmetry).pad(2);
+            let restored = transformed.transform(symmetry.inverse()).trim();
+            assert_eq!(restored.fingerprint(), question.fingerprint());
+            assert_eq!(
+                symmetry
+                    .inverse()
+                    .transform_lurd(&symmetry.transform_lurd(lurd)),
+                lurd
+            );
+        }
+    }
+
+    const LEVEL: &str = " ######\n #    #\n #@$  #\n ### .#\n   ####";
+}
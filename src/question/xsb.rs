@@ -0,0 +1,505 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{ParseError, Position, Question, QuestionCollection, Square};
+
+const WALL_CHAR: char = '#';
+const SPACE_CHAR: char = ' ';
+const BOX_CHAR: char = '$';
+const TARGET_CHAR: char = '.';
+const PLACED_CHAR: char = '*';
+const START_CHAR: char = '@';
+const START_TARGET_CHAR: char = '+';
+
+/// Characters that are also used for empty floor, since spaces are easily lost.
+const ALT_SPACE_CHARS: [char; 2] = ['-', '_'];
+
+fn is_space_char(c: char) -> bool {
+    c == SPACE_CHAR || ALT_SPACE_CHARS.contains(&c)
+}
+
+fn is_board_char(c: char) -> bool {
+    is_space_char(c)
+        || matches!(
+            c,
+            WALL_CHAR | BOX_CHAR | TARGET_CHAR | PLACED_CHAR | START_CHAR | START_TARGET_CHAR
+        )
+}
+
+/// Separates rows when a level is written on a single line.
+const ROW_SEPARATOR: char = '|';
+
+/// The longest run that a run-length encoded row may contain.
+const MAX_RUN: usize = 1000;
+
+/// The most squares a level may have, so that a broken file can't use up all the memory.
+const MAX_SQUARES: usize = 1 << 20;
+
+/// Returns true if `line` is a row of a level rather than metadata. Rows may be
+/// run-length encoded.
+pub(crate) fn is_board_line(line: &str) -> bool {
+    line.contains(WALL_CHAR)
+        && line
+            .chars()
+            .all(|c| is_board_char(c) || c.is_ascii_digit() || c == ROW_SEPARATOR)
+}
+
+/// Expands a run-length encoded line such as `4#|#@$.#|4#`, where a number repeats the
+/// character after it and `|` starts a new row. Plain rows are returned as they are.
+fn expand_rle(line: &str) -> Result<Vec<String>, ParseError> {
+    let mut rows = vec![String::new()];
+    let mut count = None;
+    for c in line.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            let run = count.unwrap_or(0) * 10 + digit as usize;
+            if run > MAX_RUN {
+                return Err(ParseError::new(format!("run longer than {}", MAX_RUN)));
+            }
+            count = Some(run);
+        } else if c == ROW_SEPARATOR {
+            if count.is_some() {
+                return Err(ParseError::new("run length without a square"));
+            }
+            rows.push(String::new());
+        } else {
+            let row = rows.last_mut().unwrap();
+            row.extend(std::iter::repeat_n(c, count.take().unwrap_or(1)));
+            if row.len() > MAX_SQUARES {
+                return Err(ParseError::new("row too long"));
+            }
+        }
+    }
+    if count.is_some() {
+        return Err(ParseError::new("run length without a square"));
+    }
+    Ok(rows)
+}
+
+/// Run-length encodes `row`, writing spaces as `-` so they can't be lost.
+fn encode_rle(row: &str) -> String {
+    let mut encoded = String::new();
+    let mut chars = row
+        .chars()
+        .map(|c| if c == SPACE_CHAR { '-' } else { c })
+        .peekable();
+    while let Some(c) = chars.next() {
+        let mut run = 1;
+        while chars.next_if_eq(&c).is_some() {
+            run += 1;
+        }
+        if run > 1 {
+            encoded.push_str(&run.to_string());
+        }
+        encoded.push(c);
+    }
+    encoded
+}
+
+impl Question {
+    /// Returns the character used to draw the square at `pos`.
+    pub(super) fn char_at(&self, pos: Position) -> char {
+        let (is_box, is_target) = (self.boxes.contains(&pos), self.targets.contains(&pos));
+        match self[pos] {
+            Square::Wall => WALL_CHAR,
+            Square::Space if is_box && is_target => PLACED_CHAR,
+            Square::Space if is_box => BOX_CHAR,
+            Square::Space if pos == self.start && is_target => START_TARGET_CHAR,
+            Square::Space if pos == self.start => START_CHAR,
+            Square::Space if is_target => TARGET_CHAR,
+            Square::Space => SPACE_CHAR,
+        }
+    }
+
+    /// Returns the walls that are written out as spaces: those outside the level that are
+    /// connected to the edge of the grid without touching any floor, including diagonally.
+    ///
+    /// Rows made up entirely of such walls are written out as walls, since a blank line
+    /// would end the level, so they are never included.
+    fn exterior_walls(&self) -> HashSet<Position> {
+        self.walls_outside(true)
+    }
+
+    /// Returns every wall outside the level, including those in rows with nothing else.
+    pub(crate) fn outside(&self) -> HashSet<Position> {
+        self.walls_outside(false)
+    }
+
+    fn walls_outside(&self, skip_full_rows: bool) -> HashSet<Position> {
+        let is_outside = |x: usize, y: usize| {
+            let (x0, y0) = (x.saturating_sub(1), y.saturating_sub(1));
+            let (x1, y1) = ((x + 1).min(self.width - 1), (y + 1).min(self.height - 1));
+            (y0..=y1).all(|y| (x0..=x1).all(|x| self.grid[y][x] == Square::Wall))
+        };
+        let outside = (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| is_outside(x, y))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let full_rows = outside
+            .iter()
+            .map(|row| row.iter().all(|&o| o))
+            .collect::<Vec<_>>();
+        let candidate =
+            |Position(x, y): Position| outside[y][x] && !(skip_full_rows && full_rows[y]);
+
+        let mut bag = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| Position(x, y)))
+            .filter(|&Position(x, y)| {
+                x == 0 || y == 0 || x + 1 == self.width || y + 1 == self.height
+            })
+            .filter(|&pos| candidate(pos))
+            .collect::<Vec<_>>();
+        let mut exterior = bag.iter().copied().collect::<HashSet<_>>();
+
+        while let Some(Position(x, y)) = bag.pop() {
+            let neighbours = [
+                y.checked_sub(1).map(|y| Position(x, y)),
+                (x + 1 < self.width).then(|| Position(x + 1, y)),
+                (y + 1 < self.height).then(|| Position(x, y + 1)),
+                x.checked_sub(1).map(|x| Position(x, y)),
+            ];
+            for pos in neighbours.into_iter().flatten() {
+                if candidate(pos) && exterior.insert(pos) {
+                    bag.push(pos);
+                }
+            }
+        }
+
+        exterior
+    }
+}
+
+impl Question {
+    /// Returns the rows of the grid as they are written in XSB format.
+    pub(crate) fn grid_lines(&self) -> Vec<String> {
+        let exterior = self.exterior_walls();
+        (0..self.height)
+            .map(|y| {
+                let row = (0..self.width)
+                    .map(|x| match Position(x, y) {
+                        pos if exterior.contains(&pos) => SPACE_CHAR,
+                        pos => self.char_at(pos),
+                    })
+                    .collect::<String>();
+                row.trim_end().to_string()
+            })
+            .collect()
+    }
+}
+
+impl Question {
+    /// Returns the level written on a single run-length encoded line, with `|`
+    /// between rows, followed by its metadata.
+    pub fn to_rle(&self) -> String {
+        let rows = self
+            .grid_lines()
+            .iter()
+            .map(|row| encode_rle(row))
+            .collect::<Vec<_>>();
+        let mut lines = vec![rows.join(&ROW_SEPARATOR.to_string())];
+        if !self.metadata.is_empty() {
+            lines.push(write_metadata(&self.metadata));
+        }
+        lines.join("\n")
+    }
+}
+
+impl QuestionCollection {
+    /// Returns the collection with each level written on a single run-length encoded line.
+    pub fn to_rle(&self) -> String {
+        let header = (!self.metadata.is_empty()).then(|| write_metadata(&self.metadata));
+        let levels = header
+            .into_iter()
+            .chain(self.iter().map(|q| q.to_rle()))
+            .collect::<Vec<_>>();
+        levels.join("\n\n")
+    }
+}
+
+impl fmt::Display for Question {
+    /// Writes the level in XSB format, followed by its metadata.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = self.grid_lines();
+        if !self.metadata.is_empty() {
+            lines.push(write_metadata(&self.metadata));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl fmt::Display for QuestionCollection {
+    /// Writes the collection's metadata as a header, followed by each level.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = (!self.metadata.is_empty()).then(|| write_metadata(&self.metadata));
+        let levels = header
+            .into_iter()
+            .chain(self.iter().map(|q| q.to_string()))
+            .collect::<Vec<_>>();
+        write!(f, "{}", levels.join("\n\n"))
+    }
+}
+
+/// Writes each entry as a `Key: value` line. Values spanning several lines are
+/// written between a `Key:` line and a `Key-End:` line.
+pub(crate) fn write_metadata(metadata: &[(String, String)]) -> String {
+    metadata
+        .iter()
+        .map(|(k, v)| match v.contains('\n') {
+            true => format!("{0}:\n{1}\n{0}-End:", k, v),
+            false => format!("{}: {}", k, v),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parses a `Key: value` line.
+fn parse_metadata_line(line: &str) -> Option<(String, String)> {
+    let (key, value) = line.split_once(':')?;
+    Some((key.trim().to_string(), value.trim().to_string()))
+}
+
+/// Parses the metadata entry at the start of `lines`, returning it and the number of
+/// lines it took up. A `Key:` line with no value followed later by a `Key-End:` line
+/// holds all the lines in between.
+pub(crate) fn parse_metadata_entry(lines: &[&str]) -> Option<((String, String), usize)> {
+    let (key, value) = parse_metadata_line(lines.first()?)?;
+    if value.is_empty() {
+        let end = format!("{}-End:", key);
+        let end_idx = lines
+            .iter()
+            .position(|line| line.trim().eq_ignore_ascii_case(&end));
+        if let Some(end_idx) = end_idx {
+            return Some(((key, lines[1..end_idx].join("\n")), end_idx + 1));
+        }
+    }
+    Some(((key, value), 1))
+}
+
+/// Adds a line of free text to the `Comment` entry of `metadata`, starting one at the
+/// end if there isn't one yet.
+fn add_comment(metadata: &mut Vec<(String, String)>, line: &str) {
+    match metadata
+        .iter_mut()
+        .find(|(k, _)| k.eq_ignore_ascii_case("Comment"))
+    {
+        Some((_, v)) => {
+            v.push('\n');
+            v.push_str(line.trim());
+        }
+        None => metadata.push(("Comment".to_string(), line.trim().to_string())),
+    }
+}
+
+/// Parses lines that only hold metadata. Lines that aren't `Key: value` are kept as
+/// comments.
+fn parse_metadata(lines: &[&str]) -> Vec<(String, String)> {
+    let mut metadata = vec![];
+    let mut idx = 0;
+    while idx < lines.len() {
+        match parse_metadata_entry(&lines[idx..]) {
+            Some((entry, len)) => {
+                metadata.push(entry);
+                idx += len;
+            }
+            None => {
+                add_comment(&mut metadata, lines[idx]);
+                idx += 1;
+            }
+        }
+    }
+    metadata
+}
+
+/// Splits a chunk of lines into levels. Usually this is the whole chunk, but each line
+/// containing a `|` holds a whole level, so they can follow each other without blank lines.
+/// Metadata goes with the level above it.
+fn split_single_line_levels<'a>(chunk: &[&'a str]) -> Vec<Vec<&'a str>> {
+    let mut levels: Vec<Vec<&str>> = vec![];
+    for &line in chunk {
+        let starts_level = is_board_line(line) && line.contains(ROW_SEPARATOR);
+        match levels.last_mut() {
+            Some(level) if !starts_level || !level.iter().any(|l| is_board_line(l)) => {
+                level.push(line)
+            }
+            _ => levels.push(vec![line]),
+        }
+    }
+    levels
+}
+
+impl FromStr for QuestionCollection {
+    type Err = ParseError;
+
+    /// Parses levels in XSB format separated by blank lines. Metadata before the first
+    /// level describes the collection.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().peekable();
+        let mut collection = QuestionCollection::default();
+        while lines.peek().is_some() {
+            let chunk = lines
+                .by_ref()
+                .take_while(|line| !line.chars().all(char::is_whitespace))
+                .collect::<Vec<_>>();
+
+            if chunk.is_empty() {
+                continue;
+            }
+
+            if !chunk.iter().any(|line| is_board_line(line)) {
+                let metadata = parse_metadata(&chunk);
+                match collection.questions.last_mut() {
+                    Some(question) => question.metadata.extend(metadata),
+                    None => collection.metadata.extend(metadata),
+                }
+                continue;
+            }
+
+            for level in split_single_line_levels(&chunk) {
+                let puzzle = Question::from_str(&level.join("\n")).map_err(|err| {
+                    ParseError::new(format!("error on grid {}, {}", collection.len() + 1, err))
+                })?;
+                collection.push(puzzle);
+            }
+        }
+
+        Ok(collection)
+    }
+}
+
+/// Returns which squares of `rows` are outside of the level: the empty floor that
+/// is connected to the edge of the grid. Missing squares at the end of short rows
+/// count as empty floor.
+fn find_exterior(rows: &[Vec<char>], width: usize) -> Vec<Vec<bool>> {
+    let height = rows.len();
+    let is_space = |x: usize, y: usize| rows[y].get(x).is_none_or(|&c| is_space_char(c));
+
+    let mut exterior = vec![vec![false; width]; height];
+    let mut bag = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| x == 0 || y == 0 || x + 1 == width || y + 1 == height)
+        .filter(|&(x, y)| is_space(x, y))
+        .collect::<Vec<_>>();
+    for &(x, y) in bag.iter() {
+        exterior[y][x] = true;
+    }
+
+    while let Some((x, y)) = bag.pop() {
+        let neighbours = [
+            y.checked_sub(1).map(|y| (x, y)),
+            (x + 1 < width).then_some((x + 1, y)),
+            (y + 1 < height).then_some((x, y + 1)),
+            x.checked_sub(1).map(|x| (x, y)),
+        ];
+        for (nx, ny) in neighbours.into_iter().flatten() {
+            if !exterior[ny][nx] && is_space(nx, ny) {
+                exterior[ny][nx] = true;
+                bag.push((nx, ny));
+            }
+        }
+    }
+
+    exterior
+}
+
+impl FromStr for Question {
+    type Err = ParseError;
+
+    /// Parses a level in XSB format. Lines of the form `Key: value` before or after
+    /// the grid are kept as metadata, and any other text as a comment. Rows may be
+    /// run-length encoded.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = s.lines().collect::<Vec<_>>();
+        let mut board_lines = vec![];
+        let mut metadata = vec![];
+        let mut grid_ended = false;
+        let mut idx = 0;
+        while idx < lines.len() {
+            let line = lines[idx];
+            if is_board_line(line) {
+                if grid_ended {
+                    return Err(ParseError::new("metadata in the middle of the grid"));
+                }
+                board_lines.extend(expand_rle(line)?);
+                idx += 1;
+            } else if let Some((entry, len)) = parse_metadata_entry(&lines[idx..]) {
+                grid_ended = !board_lines.is_empty();
+                metadata.push(entry);
+                idx += len;
+            } else if line.trim().is_empty() {
+                idx += 1;
+            } else {
+                grid_ended = !board_lines.is_empty();
+                add_comment(&mut metadata, line);
+                idx += 1;
+            }
+        }
+
+        let rows = board_lines
+            .iter()
+            .map(|row| row.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let width = rows
+            .iter()
+            .map(|row| row.len())
+            .max()
+            .ok_or_else(|| ParseError::new("Puzzle was empty"))?;
+        if width.saturating_mul(rows.len()) > MAX_SQUARES {
+            return Err(ParseError::new(format!(
+                "level larger than {} squares",
+                MAX_SQUARES
+            )));
+        }
+        let exterior = find_exterior(&rows, width);
+
+        let mut boxes = HashSet::new();
+        let mut targets = HashSet::new();
+        let mut start = None;
+
+        let mut grid = vec![];
+        for (y, row) in rows.iter().enumerate() {
+            let mut row_squares = Vec::with_capacity(width);
+            for (x, &is_exterior) in exterior[y].iter().enumerate() {
+                let c = row.get(x).copied().unwrap_or(SPACE_CHAR);
+                let pos = Position(x, y);
+                let square = match c {
+                    WALL_CHAR => Square::Wall,
+                    c if is_space_char(c) && is_exterior => Square::Wall,
+                    c if is_space_char(c) => Square::Space,
+                    _ => {
+                        if matches!(c, TARGET_CHAR | PLACED_CHAR | START_TARGET_CHAR) {
+                            targets.insert(pos);
+                        }
+                        if matches!(c, BOX_CHAR | PLACED_CHAR) {
+                            boxes.insert(pos);
+                        }
+                        if matches!(c, START_CHAR | START_TARGET_CHAR) {
+                            match start {
+                                Some(_) => return Err(ParseError::new("multiple start positions")),
+                                None => start = Some(pos),
+                            };
+                        }
+                        Square::Space
+                    }
+                };
+                row_squares.push(square);
+            }
+            grid.push(row_squares);
+        }
+
+        Ok(Self {
+            width,
+            height: rows.len(),
+            grid,
+            boxes,
+            targets,
+            start: start.ok_or_else(|| ParseError::new("no start position"))?,
+            metadata,
+            solutions: vec![],
+            source: None,
+        })
+    }
+}
@@ -1,8 +1,15 @@
 use std::error::Error;
 use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use crate::question::QuestionCollection;
+use crate::question::{LevelSource, QuestionCollection};
+
+mod glob;
+pub mod slc;
+pub mod sok;
+mod xml;
 
 pub struct Config<'a> {
     pub filename: &'a str,
@@ -10,7 +17,7 @@ pub struct Config<'a> {
 }
 
 impl<'a> Config<'a> {
-    pub fn new(args: &'a [String]) -> Result<Config, Box<dyn Error>> {
+    pub fn new(args: &'a [String]) -> Result<Config<'a>, Box<dyn Error>> {
         if args.len() <= 1 || args.len() > 3 {
             return Err(format!(
                 "Incorrect number of args: got {}, must have 2 or 3.",
@@ -29,12 +36,114 @@ impl<'a> Config<'a> {
     }
 }
 
+/// The extensions of files that are loaded when reading a whole directory, or the
+/// files matching a pattern.
+const LEVEL_EXTENSIONS: [&str; 4] = ["xsb", "txt", "sok", "slc"];
+
 pub fn read(config: &Config) -> Result<QuestionCollection, Box<dyn Error>> {
-    let filepath = format!("puzzles/{}", config.filename);
-    let contents = fs::read_to_string(filepath)?;
-    let puzzles = QuestionCollection::from_str(&contents)?;
+    read_path(config.filename)
+}
+
+/// Reads all the levels at `path`, which may be a file, `-` for stdin, a directory
+/// or a pattern containing `*` or `?` wildcards.
+///
+/// Each level remembers the file it came from and its position in it. If `path`
+/// doesn't exist but `puzzles/<path>` does, that is read instead.
+pub fn read_path(path: &str) -> Result<QuestionCollection, Box<dyn Error>> {
+    if path == "-" {
+        let mut contents = vec![];
+        io::stdin().read_to_end(&mut contents)?;
+        return parse_file(path, &decode(contents));
+    }
+
+    let files = if glob::is_pattern(path) {
+        let mut files = glob::expand(path)?;
+        files.retain(|file| is_level_file(file));
+        files
+    } else {
+        let mut path = PathBuf::from(path);
+        if !path.exists() && Path::new("puzzles").join(&path).exists() {
+            path = Path::new("puzzles").join(path);
+        }
+        if path.is_dir() {
+            level_files(&path)?
+        } else {
+            vec![path]
+        }
+    };
+
+    let mut collections = files
+        .iter()
+        .map(|file| {
+            let contents = decode(fs::read(file)?);
+            parse_file(&file.to_string_lossy(), &contents)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match collections.len() {
+        0 => Err(format!("no level files found at {}", path).into()),
+        1 => Ok(collections.remove(0)),
+        _ => {
+            let mut all = QuestionCollection::default();
+            for collection in collections {
+                all.append(collection);
+            }
+            Ok(all)
+        }
+    }
+}
+
+/// Returns the level files directly inside `dir`, sorted by name.
+fn level_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_level_file(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Returns true if `path` is a file with one of the `LEVEL_EXTENSIONS`.
+fn is_level_file(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    path.is_file() && extension.is_some_and(|ext| LEVEL_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Parses the contents of the file at `path`, picking the format from its extension,
+/// or from its contents if the extension isn't known.
+fn parse_file(path: &str, contents: &str) -> Result<QuestionCollection, Box<dyn Error>> {
+    let extension = Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+
+    let mut collection = match extension.as_deref() {
+        Some("slc") => slc::parse(contents),
+        Some("sok") => sok::parse(contents),
+        _ if contents.trim_start().starts_with('<') => slc::parse(contents),
+        // `.sok` is a superset of plain XSB, so fall back to it for titles and comments.
+        _ => QuestionCollection::from_str(contents).or_else(|_| sok::parse(contents)),
+    }
+    .map_err(|err| format!("{}: {}", path, err))?;
+
+    for (index, question) in collection.iter_mut().enumerate() {
+        question.set_source(LevelSource {
+            path: path.to_string(),
+            index,
+        });
+    }
+    Ok(collection)
+}
 
-    Ok(puzzles)
+/// Decodes the contents of a file as UTF-8, falling back to Latin-1 which many
+/// older level collections are saved in.
+fn decode(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes)
+        .unwrap_or_else(|err| err.into_bytes().into_iter().map(char::from).collect())
 }
 
 #[cfg(test)]
@@ -42,7 +151,7 @@ pub mod test_config {
 
     use super::*;
 
-    pub fn create_config(filename: &str) -> Config {
+    pub fn create_config(filename: &str) -> Config<'_> {
         Config {
             filename,
             question_number: None,
@@ -54,5 +163,33 @@ pub mod test_config {
     }
 
     #[test]
-    fn test() {}
+    fn test() {
+        let collection = create_collection("original.txt");
+        assert_eq!(collection[0].title(), Some("Level 1"));
+        assert_eq!(
+            collection[49].get_metadata("author"),
+            Some("Thinking Rabbit")
+        );
+    }
+
+    #[test]
+    fn read_paths() {
+        let collection = read_path("original.txt").unwrap();
+        assert_eq!(collection.len(), 50);
+        let source = collection[4].source().unwrap();
+        assert_eq!((source.path.as_str(), source.index), ("original.txt", 4));
+
+        let matched = read_path("origin?l.t*").unwrap();
+        assert_eq!(matched.len(), 50);
+        assert!(read_path("no_such_file_*.txt").is_err());
+
+        // Patterns skip directories and files that don't hold levels.
+        let dir = std::env::temp_dir().join(format!("sokoban-glob-{}", std::process::id()));
+        fs::create_dir_all(dir.join("more.xsb")).unwrap();
+        fs::write(dir.join("level.xsb"), "#####\n#@$.#\n#####").unwrap();
+        fs::write(dir.join("notes.md"), "# Notes").unwrap();
+        let matched = read_path(&dir.join("*").to_string_lossy());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(matched.unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,105 @@
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Returns true if `path` contains any wildcards.
+pub fn is_pattern(path: &str) -> bool {
+    path.contains(['*', '?'])
+}
+
+/// Returns every file matching `pattern`, sorted. `*` matches any run of characters
+/// and `?` matches a single character, but neither matches a path separator.
+pub fn expand(pattern: &str) -> io::Result<Vec<PathBuf>> {
+    let mut paths = vec![PathBuf::new()];
+    for component in Path::new(pattern).components() {
+        let part = match component {
+            Component::Normal(part) => part.to_string_lossy(),
+            other => {
+                for path in paths.iter_mut() {
+                    path.push(other.as_os_str());
+                }
+                continue;
+            }
+        };
+
+        if !is_pattern(&part) {
+            for path in paths.iter_mut() {
+                path.push(part.as_ref());
+            }
+            continue;
+        }
+
+        let mut matched = vec![];
+        for dir in paths {
+            let entries = match fs::read_dir(if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                &dir
+            }) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries {
+                let name = entry?.file_name();
+                if matches(&part, &name.to_string_lossy()) {
+                    matched.push(dir.join(name));
+                }
+            }
+        }
+        paths = matched;
+    }
+
+    let mut paths = paths
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Returns true if `name` matches the wildcard `pattern`.
+fn matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (
+        pattern.chars().collect::<Vec<_>>(),
+        name.chars().collect::<Vec<_>>(),
+    );
+
+    // Backtrack to just after the last `*` when a match fails.
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*.sok", "levels.sok"));
+        assert!(matches("level?.*", "level1.txt"));
+        assert!(matches("*a*b", "xxaxxb"));
+        assert!(!matches("*.sok", "levels.slc"));
+        assert!(!matches("level?", "level10"));
+    }
+}
@@ -0,0 +1,192 @@
+use std::str::FromStr;
+
+use super::xml::{self, Element};
+use crate::question::{ParseError, Question, QuestionCollection};
+
+/// The elements the SLC schema allows at the top of a collection, besides the levels.
+const COLLECTION_ELEMENTS: [&str; 4] = ["Title", "Description", "Email", "Url"];
+
+/// The metadata kept in attributes of the `LevelCollection` and of each `Level`.
+/// `Author` isn't in the schema, but other programs ignore attributes they don't know.
+const ATTRIBUTES: [&str; 2] = ["Copyright", "Author"];
+
+/// Parses a Sokoban Level Collection (`.slc`) XML file.
+///
+/// The collection's `Title`, `Description`, `Email` and `Url` become its metadata,
+/// along with the `Copyright` and `Author` of the `LevelCollection`. Each level's
+/// `Id` becomes its title.
+pub fn parse(s: &str) -> Result<QuestionCollection, ParseError> {
+    let root = xml::parse(s)?;
+    if root.name != "SokobanLevels" {
+        return Err(ParseError::Text(format!(
+            "expected <SokobanLevels> but found <{}>",
+            root.name
+        )));
+    }
+
+    let mut collection = QuestionCollection::default();
+    for element in root.elements() {
+        if element.name != "LevelCollection" {
+            collection.set_metadata(&element.name, element.text().trim());
+            continue;
+        }
+
+        for key in ATTRIBUTES {
+            if let Some(value) = element.attribute(key) {
+                collection.set_metadata(key, value);
+            }
+        }
+        for level in element.elements().filter(|el| el.name == "Level") {
+            let question = parse_level(level).map_err(|err| {
+                ParseError::Text(format!("error on level {}, {}", collection.len() + 1, err))
+            })?;
+            collection.push(question);
+        }
+    }
+
+    Ok(collection)
+}
+
+fn parse_level(level: &Element) -> Result<Question, ParseError> {
+    let rows = level
+        .elements()
+        .filter(|el| el.name == "L")
+        .map(|el| el.text())
+        .collect::<Vec<_>>();
+
+    let mut question = Question::from_str(&rows.join("\n"))?;
+    if let Some(id) = level.attribute("Id") {
+        question.set_metadata("Title", id);
+    }
+    for key in ATTRIBUTES {
+        if let Some(value) = level.attribute(key) {
+            question.set_metadata(key, value);
+        }
+    }
+    Ok(question)
+}
+
+/// Writes `collection` as a Sokoban Level Collection (`.slc`) XML file.
+///
+/// Levels without a title use their number as their `Id`. Collection metadata that the
+/// schema has no element for is added to the end of the `Description`, and the
+/// `Author` of the collection and each level is kept in an `Author` attribute.
+pub fn to_string(collection: &QuestionCollection) -> String {
+    let mut lines = vec![
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string(),
+        r#"<SokobanLevels xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:noNamespaceSchemaLocation="SokobanLev.xsd">"#.to_string(),
+    ];
+
+    let is_known = |key: &str| {
+        COLLECTION_ELEMENTS
+            .iter()
+            .chain(ATTRIBUTES.iter())
+            .any(|k| k.eq_ignore_ascii_case(key))
+    };
+    let extra = collection
+        .metadata()
+        .iter()
+        .filter(|(key, _)| !is_known(key))
+        .map(|(key, value)| format!("{}: {}", key, value))
+        .collect::<Vec<_>>();
+    for name in COLLECTION_ELEMENTS {
+        let mut value = collection.get_metadata(name).map(str::to_string);
+        if name == "Description" && !extra.is_empty() {
+            value = Some(
+                value
+                    .into_iter()
+                    .chain(extra.iter().cloned())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+        }
+        if let Some(value) = value {
+            lines.push(format!("  <{0}>{1}</{0}>", name, xml::escape(&value)));
+        }
+    }
+
+    let levels = collection
+        .iter()
+        .map(|question| (question, question.grid_lines()))
+        .collect::<Vec<_>>();
+    let max_width = levels.iter().map(|(_, rows)| width(rows)).max();
+    let max_height = levels.iter().map(|(_, rows)| rows.len()).max();
+
+    lines.push(format!(
+        r#"  <LevelCollection{} MaxWidth="{}" MaxHeight="{}">"#,
+        attributes(|key| collection.get_metadata(key)),
+        max_width.unwrap_or(0),
+        max_height.unwrap_or(0),
+    ));
+
+    for (idx, (question, rows)) in levels.iter().enumerate() {
+        let id = question
+            .title()
+            .map(str::to_string)
+            .unwrap_or_else(|| (idx + 1).to_string());
+        lines.push(format!(
+            r#"    <Level Id="{}" Width="{}" Height="{}"{}>"#,
+            xml::escape(&id),
+            width(rows),
+            rows.len(),
+            attributes(|key| question.get_metadata(key))
+        ));
+        for row in rows {
+            lines.push(format!("      <L>{}</L>", xml::escape(row)));
+        }
+        lines.push("    </Level>".to_string());
+    }
+
+    lines.push("  </LevelCollection>".to_string());
+    lines.push("</SokobanLevels>".to_string());
+    lines.join("\n") + "\n"
+}
+
+/// Returns the `ATTRIBUTES` that `metadata` has a value for, written out.
+fn attributes<'a>(metadata: impl Fn(&str) -> Option<&'a str>) -> String {
+    ATTRIBUTES
+        .iter()
+        .filter_map(|key| Some(format!(r#" {}="{}""#, key, xml::escape(metadata(key)?))))
+        .collect()
+}
+
+fn width(rows: &[String]) -> usize {
+    rows.iter()
+        .map(|row| row.chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slc_round_trip() {
+        let mut collection =
+            QuestionCollection::from_str(include_str!("../../original.txt")).unwrap();
+        collection.set_metadata("Title", "Original & Extra");
+        collection.set_metadata("Copyright", "1982");
+        collection.set_metadata("Set", "Classic");
+
+        let text = to_string(&collection);
+        assert!(!text.contains("<Set>"));
+        let parsed = parse(&text).unwrap();
+        assert_eq!(parsed.title(), Some("Original & Extra"));
+        assert_eq!(parsed.get_metadata("Copyright"), Some("1982"));
+        assert_eq!(parsed.get_metadata("Description"), Some("Set: Classic"));
+        assert_eq!(parsed.len(), collection.len());
+        for (a, b) in parsed.iter().zip(collection.iter()) {
+            assert_eq!(a.grid_lines(), b.grid_lines());
+            assert_eq!(a.title(), b.title());
+            assert_eq!(a.get_metadata("Author"), b.get_metadata("Author"));
+            assert_eq!(a.get_metadata("Copyright"), None);
+        }
+
+        let text = "<SokobanLevels><LevelCollection><Level><L>#$.#</L></Level></LevelCollection></SokobanLevels>";
+        assert_eq!(
+            parse(text).unwrap_err().to_string(),
+            "error on level 1, no start position"
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use std::str::FromStr;
+
+use crate::question::{
+    is_board_line, parse_metadata_entry, write_metadata, ParseError, Question, QuestionCollection,
+    SavedSolution,
+};
+
+/// The longest run a run-length encoded solution may contain.
+const MAX_RUN: usize = 1000;
+
+/// Returns true if `line` only contains LURD moves, which may be run-length encoded.
+fn is_lurd_line(line: &str) -> bool {
+    line.chars().any(|c| c.is_ascii_alphabetic())
+        && line
+            .chars()
+            .all(|c| "lurdLURD".contains(c) || c.is_ascii_digit())
+}
+
+/// Expands run-length encoded moves such as `3l2U`, where a number repeats the move
+/// after it. Returns `None` if a number isn't followed by a move or is too big.
+fn expand_runs(line: &str) -> Option<String> {
+    let mut moves = String::new();
+    let mut count = None;
+    for c in line.chars() {
+        match c.to_digit(10) {
+            Some(digit) => {
+                let run = count.unwrap_or(0) * 10 + digit as usize;
+                if run > MAX_RUN {
+                    return None;
+                }
+                count = Some(run);
+            }
+            None => moves.extend(std::iter::repeat_n(c, count.take().unwrap_or(1))),
+        }
+    }
+    count.is_none().then_some(moves)
+}
+
+/// If `line` starts a solution or snapshot, returns whether it is a snapshot.
+fn parse_heading(line: &str) -> Option<bool> {
+    let lower = line.to_ascii_lowercase();
+    let (word, snapshot) = if lower.starts_with("solution") {
+        ("solution", false)
+    } else if lower.starts_with("snapshot") {
+        ("snapshot", true)
+    } else {
+        return None;
+    };
+
+    // Allow headings such as "Solution (moves 12, pushes 3)" or "Solution/Pushes".
+    match lower[word.len()..].chars().next() {
+        None | Some(' ' | '(' | '/' | ':' | '-') => Some(snapshot),
+        _ => None,
+    }
+}
+
+/// Returns true if `lines[idx]`, the last line before the grid starting at `grid`, is
+/// the title of the level.
+///
+/// Titles can look like metadata or a solution, such as `Part 1: Start` or `Dull`, so
+/// a line like that is only taken as a title if it doesn't follow straight on from
+/// the line above it. Metadata also has to be right above the grid.
+fn is_title(lines: &[&str], idx: usize, grid: usize) -> bool {
+    let line = lines[idx].trim();
+    if line.is_empty() || line.starts_with("::") || parse_heading(line).is_some() {
+        return false;
+    }
+    let follows_on = idx > 0 && !lines[idx - 1].trim().is_empty();
+    if parse_metadata_entry(&[line]).is_some() {
+        !follows_on && idx + 1 == grid
+    } else if is_lurd_line(line) {
+        !follows_on
+    } else {
+        true
+    }
+}
+
+/// Returns the title written on `line`, which may be a `Title: ...` line.
+fn title_text(line: &str) -> &str {
+    match line.split_once(':') {
+        Some((key, value)) if key.trim().eq_ignore_ascii_case("Title") => value.trim(),
+        _ => line.trim(),
+    }
+}
+
+/// Everything that can appear between two grids.
+#[derive(Default)]
+struct Sections {
+    metadata: Vec<(String, String)>,
+    solutions: Vec<SavedSolution>,
+    comments: Vec<String>,
+}
+
+impl Sections {
+    fn parse(lines: &[&str]) -> Self {
+        let mut sections = Self::default();
+        let mut idx = 0;
+        while idx < lines.len() {
+            let line = lines[idx].trim_end();
+            idx += 1;
+
+            if line.trim().is_empty() || line.starts_with("::") {
+                continue;
+            }
+
+            if let Some(snapshot) = parse_heading(line.trim()) {
+                let mut lurd = String::new();
+                while let Some(next) = lines.get(idx).map(|l| l.trim()) {
+                    let Some(moves) = Some(next).filter(|l| is_lurd_line(l)).and_then(expand_runs)
+                    else {
+                        break;
+                    };
+                    lurd.push_str(&moves);
+                    idx += 1;
+                }
+                sections.solutions.push(SavedSolution { lurd, snapshot });
+            } else if let Some((entry, len)) = parse_metadata_entry(&lines[idx - 1..]) {
+                if entry.0.eq_ignore_ascii_case("Comment") {
+                    sections.comments.push(entry.1);
+                } else {
+                    sections.metadata.push(entry);
+                }
+                idx += len - 1;
+            } else {
+                sections.comments.push(line.to_string());
+            }
+        }
+        sections
+    }
+
+    /// Returns the metadata, with all the comments joined into a single entry.
+    fn into_metadata(mut self) -> Vec<(String, String)> {
+        let comment = self.comments.join("\n");
+        if !comment.trim().is_empty() {
+            self.metadata.push(("Comment".to_string(), comment));
+        }
+        self.metadata
+    }
+}
+
+/// Parses a `.sok` file, keeping any solutions and snapshots saved with each level.
+///
+/// Levels are written with their title on the line above the grid, and any metadata,
+/// comments, solutions and snapshots below it. Everything before the first level
+/// describes the collection. Lines starting with `::` are ignored.
+pub fn parse(s: &str) -> Result<QuestionCollection, ParseError> {
+    let lines = s.lines().collect::<Vec<_>>();
+
+    // The start and end of each grid.
+    let mut grids = vec![];
+    let mut idx = 0;
+    while idx < lines.len() {
+        if is_board_line(lines[idx]) {
+            let start = idx;
+            while idx < lines.len() && is_board_line(lines[idx]) {
+                idx += 1;
+            }
+            grids.push((start, idx));
+        } else {
+            idx += 1;
+        }
+    }
+
+    // The position of the title line above each grid, if there is one.
+    let mut titles = vec![];
+    let mut prev_end = 0;
+    for &(start, end) in grids.iter() {
+        let title = lines[prev_end..start]
+            .iter()
+            .rposition(|line| !line.trim().is_empty())
+            .map(|idx| prev_end + idx)
+            .filter(|&idx| is_title(&lines, idx, start));
+        titles.push(title);
+        prev_end = end;
+    }
+
+    let mut collection = QuestionCollection::default();
+    let header_end = match grids.first() {
+        Some(&(start, _)) => titles[0].unwrap_or(start),
+        None => lines.len(),
+    };
+    for (key, value) in Sections::parse(&lines[..header_end]).into_metadata() {
+        collection.set_metadata(key, value);
+    }
+
+    for (idx, &(start, end)) in grids.iter().enumerate() {
+        let next_start = match grids.get(idx + 1) {
+            Some(&(next_start, _)) => titles[idx + 1].unwrap_or(next_start),
+            None => lines.len(),
+        };
+
+        let mut question = Question::from_str(&lines[start..end].join("\n"))
+            .map_err(|err| ParseError::Text(format!("error on level {}, {}", idx + 1, err)))?;
+        if let Some(title) = titles[idx] {
+            question.set_metadata("Title", title_text(lines[title]));
+        }
+
+        let sections = Sections::parse(&lines[end..next_start]);
+        for solution in sections.solutions.iter().cloned() {
+            question.add_solution(solution);
+        }
+        for (key, value) in sections.into_metadata() {
+            question.set_metadata(key, value);
+        }
+        collection.push(question);
+    }
+
+    Ok(collection)
+}
+
+/// Writes `collection` in `.sok` format, including every saved solution and
+/// snapshot along with its move and push counts.
+pub fn to_string(collection: &QuestionCollection) -> String {
+    let mut blocks = vec![];
+    if !collection.metadata().is_empty() {
+        blocks.push(write_metadata(collection.metadata()));
+    }
+
+    for question in collection.iter() {
+        let mut lines = question
+            .title()
+            .map(str::to_string)
+            .into_iter()
+            .collect::<Vec<_>>();
+        lines.extend(question.grid_lines());
+
+        let metadata = question
+            .metadata()
+            .iter()
+            .filter(|(key, _)| !key.eq_ignore_ascii_case("Title"))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !metadata.is_empty() {
+            lines.push(write_metadata(&metadata));
+        }
+
+        for solution in question.solutions() {
+            lines.push(String::new());
+            lines.push(format!(
+                "{} (moves {}, pushes {})",
+                if solution.snapshot {
+                    "Snapshot"
+                } else {
+                    "Solution"
+                },
+                solution.move_count(),
+                solution.push_count()
+            ));
+            lines.push(solution.lurd.clone());
+        }
+        blocks.push(lines.join("\n"));
+    }
+
+    blocks.join("\n\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOK: &str = "\
+::   Saved by hand
+Title: Test Collection
+Author: Someone
+
+Level 1
+ ######
+ #    #
+ #@$  #
+ ### .#
+   ####
+Author: Someone Else
+Comment:
+Push it right,
+then down.
+Comment-End:
+
+Solution (moves 5, pushes 3)
+RRurD
+
+Snapshot
+RR
+
+Level 2
+#####
+#@$.#
+#####
+";
+
+    #[test]
+    fn sok_round_trip() {
+        let collection = parse(SOK).unwrap();
+        assert_eq!(collection.title(), Some("Test Collection"));
+        assert_eq!(collection.len(), 2);
+
+        let first = &collection[0];
+        assert_eq!(first.title(), Some("Level 1"));
+        assert_eq!(first.get_metadata("Author"), Some("Someone Else"));
+        assert_eq!(
+            first.get_metadata("Comment"),
+            Some("Push it right,\nthen down.")
+        );
+        assert_eq!(first.solutions().len(), 2);
+        assert_eq!(first.solutions()[0].lurd, "RRurD");
+        assert!(first.solutions()[1].snapshot);
+        assert_eq!(collection[1].title(), Some("Level 2"));
+
+        assert_eq!(parse(&to_string(&collection)).unwrap(), collection);
+    }
+
+    #[test]
+    fn titles_that_look_like_something_else() {
+        let text = "\
+Dull
+#####
+#@$.#
+#####
+
+Solution
+2R
+4l2r
+
+Part 2: Harder
+######
+#@$ .#
+######
+Author: Me
+";
+        let collection = parse(text).unwrap();
+        assert_eq!(collection[0].title(), Some("Dull"));
+        assert_eq!(collection[0].solutions()[0].lurd, "RRllllrr");
+        assert_eq!(collection[1].title(), Some("Part 2: Harder"));
+        assert_eq!(collection[1].get_metadata("Author"), Some("Me"));
+        assert_eq!(parse(&to_string(&collection)).unwrap(), collection);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::question::ParseError;
+
+#[derive(Debug, Clone)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    /// Returns the value of the attribute called `name`, ignoring case.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the child elements, skipping any text.
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(el) => Some(el),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// Returns all the text directly inside the element.
+    pub fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Text(text) => Some(text.as_str()),
+                Node::Element(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Parses `s` and returns its root element.
+///
+/// Only handles what level files need: elements, attributes, text, comments, CDATA
+/// and the standard entities. Namespaces and DTDs are ignored.
+pub fn parse(s: &str) -> Result<Element, ParseError> {
+    let mut parser = Parser {
+        s,
+        pos: 0,
+        depth: 0,
+    };
+    parser.skip_misc()?;
+    let root = parser.element()?;
+    parser.skip_misc()?;
+    if parser.pos < s.len() {
+        return Err(parser.error("text after the root element"));
+    }
+    Ok(root)
+}
+
+/// Escapes `s` so it can be used as text or an attribute value.
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// How deep elements can be nested. Level files need four or five levels, and each
+/// one costs a stack frame, so this stops deeply nested input overflowing the stack.
+const MAX_DEPTH: usize = 256;
+
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+    /// The number of elements the parser is inside.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn error(&self, msg: &str) -> ParseError {
+        let line = self.s[..self.pos].lines().count().max(1);
+        ParseError::Text(format!("xml error on line {}: {}", line, msg))
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            self.pos += prefix.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, prefix: &str) -> Result<(), ParseError> {
+        if self.eat(prefix) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {:?}", prefix)))
+        }
+    }
+
+    /// Moves past `end`, returning everything before it.
+    fn take_until(&mut self, end: &str) -> Result<&'a str, ParseError> {
+        match self.rest().find(end) {
+            Some(idx) => {
+                let taken = &self.rest()[..idx];
+                self.pos += idx + end.len();
+                Ok(taken)
+            }
+            None => Err(self.error(&format!("missing {:?}", end))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.s.len() - trimmed.len();
+    }
+
+    /// Skips whitespace, comments, processing instructions and doctypes.
+    fn skip_misc(&mut self) -> Result<(), ParseError> {
+        loop {
+            self.skip_whitespace();
+            if self.eat("<?") {
+                self.take_until("?>")?;
+            } else if self.eat("<!--") {
+                self.take_until("-->")?;
+            } else if self.eat("<!") {
+                self.take_until(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, ParseError> {
+        let len = self
+            .rest()
+            .find(|c: char| c.is_whitespace() || matches!(c, '>' | '/' | '='))
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err(self.error("expected a name"));
+        }
+        let name = &self.rest()[..len];
+        self.pos += len;
+        Ok(name.to_string())
+    }
+
+    fn element(&mut self) -> Result<Element, ParseError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error(&format!("elements nested more than {} deep", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        let element = self.element_inner();
+        self.depth -= 1;
+        element
+    }
+
+    fn element_inner(&mut self) -> Result<Element, ParseError> {
+        self.expect("<")?;
+        let mut element = Element {
+            name: self.name()?,
+            ..Element::default()
+        };
+
+        loop {
+            self.skip_whitespace();
+            if self.eat("/>") {
+                return Ok(element);
+            }
+            if self.eat(">") {
+                break;
+            }
+            let key = self.name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = if self.eat("\"") {
+                "\""
+            } else {
+                self.expect("'")?;
+                "'"
+            };
+            let value = unescape(self.take_until(quote)?);
+            element.attributes.push((key, value));
+        }
+
+        loop {
+            if self.eat("</") {
+                let name = self.name()?;
+                if name != element.name {
+                    return Err(self.error(&format!(
+                        "expected </{}> but found </{}>",
+                        element.name, name
+                    )));
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                return Ok(element);
+            } else if self.eat("<!--") {
+                self.take_until("-->")?;
+            } else if self.eat("<![CDATA[") {
+                let text = self.take_until("]]>")?;
+                element.children.push(Node::Text(text.to_string()));
+            } else if self.eat("<?") {
+                self.take_until("?>")?;
+            } else if self.rest().starts_with('<') {
+                element.children.push(Node::Element(self.element()?));
+            } else if self.rest().is_empty() {
+                return Err(self.error(&format!("missing </{}>", element.name)));
+            } else {
+                let len = self.rest().find('<').unwrap_or(self.rest().len());
+                let text = &self.rest()[..len];
+                self.pos += len;
+                element.children.push(Node::Text(unescape(text)));
+            }
+        }
+    }
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(idx) = rest.find('&') {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "lt" => '<',
+                "gt" => '>',
+                "amp" => '&',
+                "quot" => '"',
+                "apos" => '\'',
+                entity => {
+                    let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                    };
+                    char::from_u32(code?)?
+                }
+            };
+            Some((c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            // Leave anything that isn't a known entity as it is.
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,359 @@
+//! Drawing levels and solutions as pictures.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::question::{ParseError, Position, Question, Square};
+use crate::solve::{Game, ReplayError, Solution, StepOutcome};
+
+mod apng;
+mod gif;
+mod raster;
+mod svg;
+
+use raster::Raster;
+pub use svg::{to_svg, to_svg_with_paths};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color(pub u8, pub u8, pub u8);
+
+impl fmt::Display for Color {
+    /// Writes the color as `#rrggbb`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+/// The colors a level is drawn in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Everything outside the level.
+    pub background: Color,
+    pub wall: Color,
+    pub floor: Color,
+    pub target: Color,
+    pub box_color: Color,
+    pub box_on_target: Color,
+    pub player: Color,
+    /// The lines showing where boxes were pushed.
+    pub path: Color,
+}
+
+impl Theme {
+    pub fn names() -> &'static [&'static str] {
+        &["classic", "dark", "print"]
+    }
+
+    pub fn classic() -> Self {
+        Self {
+            background: Color(0xff, 0xff, 0xff),
+            wall: Color(0x8b, 0x5a, 0x2b),
+            floor: Color(0xf5, 0xe6, 0xc8),
+            target: Color(0xd9, 0x4f, 0x4f),
+            box_color: Color(0xe0, 0xa8, 0x3c),
+            box_on_target: Color(0x5a, 0xa0, 0x4a),
+            player: Color(0x30, 0x5f, 0xc8),
+            path: Color(0xc8, 0x30, 0x30),
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            background: Color(0x12, 0x12, 0x16),
+            wall: Color(0x4a, 0x4e, 0x5a),
+            floor: Color(0x26, 0x28, 0x30),
+            target: Color(0xe0, 0x6c, 0x75),
+            box_color: Color(0xe5, 0xc0, 0x7b),
+            box_on_target: Color(0x98, 0xc3, 0x79),
+            player: Color(0x61, 0xaf, 0xef),
+            path: Color(0xc6, 0x78, 0xdd),
+        }
+    }
+
+    /// Black and white, for printing.
+    pub fn print() -> Self {
+        Self {
+            background: Color(0xff, 0xff, 0xff),
+            wall: Color(0x40, 0x40, 0x40),
+            floor: Color(0xff, 0xff, 0xff),
+            target: Color(0x90, 0x90, 0x90),
+            box_color: Color(0xc8, 0xc8, 0xc8),
+            box_on_target: Color(0x70, 0x70, 0x70),
+            player: Color(0x00, 0x00, 0x00),
+            path: Color(0x00, 0x00, 0x00),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+impl FromStr for Theme {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "classic" => Ok(Self::classic()),
+            "dark" => Ok(Self::dark()),
+            "print" => Ok(Self::print()),
+            _ => Err(ParseError::Text(format!(
+                "unknown theme {:?}, expected one of {}",
+                s,
+                Self::names().join(", ")
+            ))),
+        }
+    }
+}
+
+/// What a square of the grid is, ignoring boxes and the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tile {
+    /// A wall that isn't next to any floor, which is left blank.
+    Outside,
+    Wall,
+    Floor,
+    Target,
+}
+
+/// A level at one moment, ready to be drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scene {
+    width: usize,
+    height: usize,
+    tiles: Vec<Vec<Tile>>,
+    boxes: HashSet<Position>,
+    player: Position,
+}
+
+impl Scene {
+    /// Returns the level at its start.
+    pub fn new(question: &Question) -> Self {
+        let outside = question.outside();
+        let tiles = question
+            .rows()
+            .enumerate()
+            .map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(x, square)| {
+                        let pos = Position::new(x, y);
+                        match square {
+                            _ if outside.contains(&pos) => Tile::Outside,
+                            Square::Wall => Tile::Wall,
+                            Square::Space if question.targets().contains(&pos) => Tile::Target,
+                            Square::Space => Tile::Floor,
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            width: question.width(),
+            height: question.height(),
+            tiles,
+            boxes: question.boxes().clone(),
+            player: question.start(),
+        }
+    }
+
+    /// Returns the level as it is in `game`, which must be being played on `question`.
+    pub fn from_game(question: &Question, game: &Game) -> Self {
+        Self {
+            boxes: game.boxes().into_iter().collect(),
+            player: game.player(),
+            ..Self::new(question)
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn tile(&self, pos: Position) -> Tile {
+        self.tiles[pos.y()][pos.x()]
+    }
+
+    pub fn has_box(&self, pos: Position) -> bool {
+        self.boxes.contains(&pos)
+    }
+
+    pub fn player(&self) -> Position {
+        self.player
+    }
+
+    /// Returns every square of the grid, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| Position::new(x, y)))
+    }
+}
+
+/// Returns the level at the start of `solution` and after each of its pushes.
+pub fn push_frames(question: &Question, solution: &Solution) -> Result<Vec<Scene>, ReplayError> {
+    frames(question, solution, true)
+}
+
+/// Returns the level at the start of `solution` and after each of its moves.
+pub fn move_frames(question: &Question, solution: &Solution) -> Result<Vec<Scene>, ReplayError> {
+    frames(question, solution, false)
+}
+
+fn frames(
+    question: &Question,
+    solution: &Solution,
+    pushes_only: bool,
+) -> Result<Vec<Scene>, ReplayError> {
+    solution.replay(question)?;
+
+    let mut game = Game::new(question);
+    let mut frames = vec![Scene::new(question)];
+    for m in solution.moves() {
+        let outcome = game.step(m.dir);
+        if !pushes_only || outcome == Ok(StepOutcome::Pushed) {
+            frames.push(Scene::from_game(question, &game));
+        }
+    }
+    Ok(frames)
+}
+
+/// Returns the squares each box was pushed through, in order, as one line for each
+/// run of pushes of the same box.
+pub fn push_paths(
+    question: &Question,
+    solution: &Solution,
+) -> Result<Vec<Vec<Position>>, ReplayError> {
+    solution.replay(question)?;
+
+    let mut paths: Vec<Vec<Position>> = vec![];
+    let mut player = question.start();
+    for m in solution.moves() {
+        // The moves were checked, so the player never steps off the grid.
+        let next = player.neighbour(m.dir).unwrap();
+        if m.push {
+            let to = next.neighbour(m.dir).unwrap();
+            match paths.last_mut() {
+                Some(path) if path.last() == Some(&next) => path.push(to),
+                _ => paths.push(vec![next, to]),
+            }
+        }
+        player = next;
+    }
+    Ok(paths)
+}
+
+/// Returns `scenes` as a looping GIF, with squares `tile` pixels across, showing each
+/// scene for `delay`. GIF only keeps delays to the hundredth of a second.
+///
+/// The scenes must all be of the same level.
+pub fn to_gif(scenes: &[Scene], theme: &Theme, tile: usize, delay: Duration) -> Vec<u8> {
+    let frames = scenes
+        .iter()
+        .map(|scene| Raster::new(scene, tile))
+        .collect::<Vec<_>>();
+    let centis = (delay.as_millis() / 10).min(u16::MAX as u128) as u16;
+    gif::encode(&frames, &raster::palette(theme), centis)
+}
+
+/// Returns `scenes` as a looping animated PNG, with squares `tile` pixels across,
+/// showing each scene for `delay`.
+///
+/// The scenes must all be of the same level.
+pub fn to_apng(scenes: &[Scene], theme: &Theme, tile: usize, delay: Duration) -> Vec<u8> {
+    let frames = scenes
+        .iter()
+        .map(|scene| Raster::new(scene, tile))
+        .collect::<Vec<_>>();
+    let millis = delay.as_millis().min(u16::MAX as u128) as u16;
+    apng::encode(&frames, &raster::palette(theme), millis)
+}
+
+/// Packs bits into bytes, lowest bits first, as both GIF and PNG compression do.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    len: u32,
+}
+
+impl BitWriter {
+    /// Starts writing after `bytes`.
+    fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            buffer: 0,
+            len: 0,
+        }
+    }
+
+    fn write(&mut self, bits: u32, len: u32) {
+        self.buffer |= bits << self.len;
+        self.len += len;
+        while self.len >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    /// Writes a Huffman code, which is packed starting from its highest bit.
+    fn write_code(&mut self, code: u32, len: u32) {
+        self.write(code.reverse_bits() >> (32 - len), len);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_and_paths() {
+        let question = Question::from_str("#######\n#@$  .#\n#  $ .#\n#######").unwrap();
+        let solution = Solution::from_str("RRRlldRR").unwrap();
+
+        let frames = push_frames(&question, &solution).unwrap();
+        assert_eq!(frames.len(), solution.push_count() + 1);
+        assert_eq!(frames[0], Scene::new(&question));
+        assert!(frames[3].has_box(Position::new(5, 1)));
+        assert_eq!(frames[5].player(), Position::new(4, 2));
+        assert_eq!(frames[0].tile(Position::new(5, 2)), Tile::Target);
+
+        assert_eq!(
+            push_paths(&question, &solution).unwrap(),
+            vec![
+                (2..=5).map(|x| Position::new(x, 1)).collect::<Vec<_>>(),
+                (3..=5).map(|x| Position::new(x, 2)).collect::<Vec<_>>(),
+            ]
+        );
+
+        let svg = to_svg_with_paths(&frames[0], &Theme::default(), 32, &[]);
+        assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("class=\"box\"").count(), 2);
+
+        let gif = to_gif(&frames, &Theme::dark(), 8, Duration::from_millis(50));
+        assert!(gif.starts_with(b"GIF89a") && gif.ends_with(b"\x3b"));
+        assert_eq!(gif.windows(2).filter(|w| w == b"\x21\xf9").count(), 6);
+        let apng = to_apng(&frames, &Theme::dark(), 8, Duration::from_millis(50));
+        assert!(apng.starts_with(b"\x89PNG") && apng.windows(4).any(|w| w == b"IEND"));
+        assert_eq!(apng.windows(4).filter(|w| w == b"fcTL").count(), 6);
+
+        assert_eq!(
+            push_frames(&question, &Solution::from_str("L").unwrap()),
+            Err(ReplayError::Blocked(0))
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use super::raster::Raster;
+use super::{BitWriter, Color};
+
+/// Returns `frames` as a looping animated PNG, showing each for `delay` milliseconds.
+///
+/// The frames must all be the same size. `palette` has at most 256 colors.
+pub(super) fn encode(frames: &[Raster], palette: &[Color], delay: u16) -> Vec<u8> {
+    let (width, height) = (frames[0].width as u32, frames[0].height as u32);
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+
+    // 8 bits per pixel, indexed color, default compression, filtering and no interlacing.
+    let mut header = vec![];
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 3, 0, 0, 0]);
+    chunk(&mut png, b"IHDR", &header);
+
+    let colors = palette
+        .iter()
+        .flat_map(|&Color(r, g, b)| [r, g, b])
+        .collect::<Vec<_>>();
+    chunk(&mut png, b"PLTE", &colors);
+
+    // The number of frames, and to loop forever.
+    let mut control = (frames.len() as u32).to_be_bytes().to_vec();
+    control.extend_from_slice(&0u32.to_be_bytes());
+    chunk(&mut png, b"acTL", &control);
+
+    // Frame control and data chunks share one sequence of numbers.
+    let mut sequence = 0u32;
+    for (idx, frame) in frames.iter().enumerate() {
+        let mut control = sequence.to_be_bytes().to_vec();
+        control.extend_from_slice(&width.to_be_bytes());
+        control.extend_from_slice(&height.to_be_bytes());
+        control.extend_from_slice(&[0; 8]);
+        control.extend_from_slice(&delay.to_be_bytes());
+        control.extend_from_slice(&1000u16.to_be_bytes());
+        control.extend_from_slice(&[0, 0]);
+        chunk(&mut png, b"fcTL", &control);
+        sequence += 1;
+
+        // Each row starts with its filter type, which is always none.
+        let stride = frame.width + 1;
+        let rows = frame
+            .pixels
+            .chunks(frame.width)
+            .flat_map(|row| std::iter::once(0).chain(row.iter().copied()))
+            .collect::<Vec<_>>();
+        let data = zlib(&rows, stride);
+        if idx == 0 {
+            chunk(&mut png, b"IDAT", &data);
+        } else {
+            let mut body = sequence.to_be_bytes().to_vec();
+            body.extend_from_slice(&data);
+            chunk(&mut png, b"fdAT", &body);
+            sequence += 1;
+        }
+    }
+
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Appends a chunk with its length and checksum.
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Compresses `data` into a zlib stream, as a single deflate block with the fixed codes.
+///
+/// Pictures of levels are mostly runs of one color, and rows the same as the row above,
+/// so only matches with the previous byte or the byte `stride` back are looked for.
+fn zlib(data: &[u8], stride: usize) -> Vec<u8> {
+    let mut out = BitWriter::new(vec![0x78, 0x01]);
+    // The last block, compressed with the fixed codes.
+    out.write(1, 1);
+    out.write(1, 2);
+
+    let distances = [1, stride]
+        .into_iter()
+        .filter(|&d| d <= 32768)
+        .collect::<Vec<_>>();
+    let mut pos = 0;
+    while pos < data.len() {
+        let longest = |distance: usize| {
+            if distance > pos {
+                return 0;
+            }
+            (0..258.min(data.len() - pos))
+                .take_while(|&i| data[pos + i] == data[pos + i - distance])
+                .count()
+        };
+        let (length, distance) = distances
+            .iter()
+            .map(|&d| (longest(d), d))
+            .max()
+            .unwrap_or((0, 0));
+
+        if length >= 3 {
+            write_match(&mut out, length as u16, distance as u16);
+            pos += length;
+        } else {
+            write_symbol(&mut out, data[pos] as u16);
+            pos += 1;
+        }
+    }
+    write_symbol(&mut out, 256);
+
+    let mut bytes = out.finish();
+    bytes.extend_from_slice(&adler32(data).to_be_bytes());
+    bytes
+}
+
+/// Writes a literal byte, the end of the block, or a length code, with the fixed codes.
+fn write_symbol(out: &mut BitWriter, symbol: u16) {
+    let symbol = symbol as u32;
+    match symbol {
+        0..=143 => out.write_code(0x30 + symbol, 8),
+        144..=255 => out.write_code(0x190 + symbol - 144, 9),
+        256..=279 => out.write_code(symbol - 256, 7),
+        _ => out.write_code(0xc0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(out: &mut BitWriter, length: u16, distance: u16) {
+    let code = LENGTH_BASES
+        .iter()
+        .rposition(|&base| base <= length)
+        .unwrap();
+    write_symbol(out, 257 + code as u16);
+    out.write((length - LENGTH_BASES[code]) as u32, LENGTH_EXTRA[code]);
+
+    let code = DISTANCE_BASES
+        .iter()
+        .rposition(|&base| base <= distance)
+        .unwrap();
+    out.write_code(code as u32, 5);
+    out.write(
+        (distance - DISTANCE_BASES[code]) as u32,
+        DISTANCE_EXTRA[code],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads bits lowest first, the way deflate packs them.
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        pos: usize,
+    }
+
+    impl BitReader<'_> {
+        fn read(&mut self, len: u32) -> usize {
+            (0..len).fold(0, |value, i| value | self.bit() << i)
+        }
+
+        /// Reads a Huffman code, which is packed starting from its highest bit.
+        fn read_code(&mut self, len: u32) -> usize {
+            (0..len).fold(0, |value, _| value << 1 | self.bit())
+        }
+
+        fn bit(&mut self) -> usize {
+            let bit = (self.bytes[self.pos / 8] >> (self.pos % 8)) & 1;
+            self.pos += 1;
+            bit as usize
+        }
+    }
+
+    /// Decompresses a zlib stream made of stored blocks and blocks with the fixed codes.
+    fn inflate(zlib: &[u8]) -> Vec<u8> {
+        assert_eq!((zlib[0] as u16 * 256 + zlib[1] as u16) % 31, 0);
+        let mut bits = BitReader {
+            bytes: &zlib[2..],
+            pos: 0,
+        };
+        let mut data = vec![];
+        loop {
+            let last = bits.read(1) == 1;
+            match bits.read(2) {
+                0 => {
+                    let start = bits.pos.div_ceil(8);
+                    let len = u16::from_le_bytes([bits.bytes[start], bits.bytes[start + 1]]);
+                    let data_start = start + 4;
+                    data.extend_from_slice(&bits.bytes[data_start..data_start + len as usize]);
+                    bits.pos = (data_start + len as usize) * 8;
+                }
+                1 => loop {
+                    let symbol = match bits.read_code(7) {
+                        code @ 0..=0x17 => 256 + code,
+                        code => match code << 1 | bits.bit() {
+                            code @ 0x30..=0xbf => code - 0x30,
+                            code @ 0xc0..=0xc7 => 280 + code - 0xc0,
+                            code => (code << 1 | bits.bit()) - 0x190 + 144,
+                        },
+                    };
+                    match symbol {
+                        0..=255 => data.push(symbol as u8),
+                        256 => break,
+                        _ => {
+                            let code = symbol - 257;
+                            let length =
+                                LENGTH_BASES[code] as usize + bits.read(LENGTH_EXTRA[code]);
+                            let code = bits.read_code(5);
+                            let distance =
+                                DISTANCE_BASES[code] as usize + bits.read(DISTANCE_EXTRA[code]);
+                            for _ in 0..length {
+                                data.push(data[data.len() - distance]);
+                            }
+                        }
+                    }
+                },
+                kind => panic!("unexpected block type {}", kind),
+            }
+            if last {
+                break;
+            }
+        }
+
+        let end = bits.pos.div_ceil(8);
+        let checksum = &bits.bytes[end..end + 4];
+        assert_eq!(checksum, adler32(&data).to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn frames_decode_to_the_pixels_drawn() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let palette = (0..8).map(|i| Color(i * 30, 0, 0)).collect::<Vec<_>>();
+        // Noise makes literals, including ones with the 9 bit codes.
+        let mut seed = 1u32;
+        let noise = (0..40 * 30)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) as u8
+            })
+            .collect();
+        let frames = [
+            Raster {
+                width: 40,
+                height: 30,
+                pixels: (0..40 * 30).map(|i| (i % 40 / 7) as u8).collect(),
+            },
+            Raster {
+                width: 40,
+                height: 30,
+                pixels: noise,
+            },
+            Raster {
+                width: 40,
+                height: 30,
+                pixels: vec![3; 40 * 30],
+            },
+        ];
+
+        let png = encode(&frames, &palette, 100);
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        let mut chunks = vec![];
+        let mut pos = 8;
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let (kind, data) = (&png[pos + 4..pos + 8], &png[pos + 8..pos + 8 + len]);
+            let crc = u32::from_be_bytes(png[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+            assert_eq!(crc, crc32(&png[pos + 4..pos + 8 + len]));
+            chunks.push((kind, data));
+            pos += 12 + len;
+        }
+        assert_eq!(pos, png.len());
+
+        let kinds = chunks.iter().map(|(kind, _)| *kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                b"IHDR", b"PLTE", b"acTL", b"fcTL", b"IDAT", b"fcTL", b"fdAT", b"fcTL", b"fdAT",
+                b"IEND"
+            ]
+        );
+
+        let images = chunks
+            .iter()
+            .filter_map(|(kind, data)| match *kind {
+                b"IDAT" => Some(&data[..]),
+                b"fdAT" => Some(&data[4..]),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for (frame, image) in frames.iter().zip(images) {
+            let rows = inflate(image);
+            let pixels = rows
+                .chunks(frame.width + 1)
+                .flat_map(|row| {
+                    assert_eq!(row[0], 0, "filter type");
+                    row[1..].iter().copied()
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(pixels, frame.pixels);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use rustc_hash::FxHashMap;
+
+use super::raster::Raster;
+use super::{BitWriter, Color};
+
+/// GIF codes are at most 12 bits, so the code table holds at most this many entries.
+const MAX_CODES: u16 = 4096;
+
+/// Returns `frames` as a looping GIF, showing each for `delay` hundredths of a second.
+///
+/// The frames must all be the same size. `palette` has at most 256 colors.
+pub(super) fn encode(frames: &[Raster], palette: &[Color], delay: u16) -> Vec<u8> {
+    let (width, height) = (frames[0].width as u16, frames[0].height as u16);
+    // The color table holds 2^(bits) colors, for some bits from 1 to 8.
+    let bits = (1..=8)
+        .find(|&bits| 1 << bits >= palette.len())
+        .unwrap_or(8);
+
+    let mut gif = b"GIF89a".to_vec();
+    gif.extend_from_slice(&width.to_le_bytes());
+    gif.extend_from_slice(&height.to_le_bytes());
+    // A global color table of 2^bits colors, then the background color and aspect ratio.
+    gif.extend_from_slice(&[0x80 | 0x70 | (bits as u8 - 1), 0, 0]);
+    for i in 0..1 << bits {
+        let Color(r, g, b) = palette.get(i).copied().unwrap_or(Color(0, 0, 0));
+        gif.extend_from_slice(&[r, g, b]);
+    }
+    // Loop forever.
+    gif.extend_from_slice(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+    for frame in frames {
+        gif.extend_from_slice(&[0x21, 0xf9, 0x04, 0x00]);
+        gif.extend_from_slice(&delay.to_le_bytes());
+        gif.extend_from_slice(&[0x00, 0x00]);
+
+        gif.push(0x2c);
+        gif.extend_from_slice(&[0, 0, 0, 0]);
+        gif.extend_from_slice(&width.to_le_bytes());
+        gif.extend_from_slice(&height.to_le_bytes());
+        gif.push(0x00);
+
+        // Codes start one bit wider than the pixels, which must be at least 2 bits.
+        let min_size = bits.max(2) as u8;
+        gif.push(min_size);
+        for block in lzw(&frame.pixels, min_size).chunks(255) {
+            gif.push(block.len() as u8);
+            gif.extend_from_slice(block);
+        }
+        gif.push(0x00);
+    }
+
+    gif.push(0x3b);
+    gif
+}
+
+/// Compresses `pixels` with the variant of LZW used by GIF.
+fn lzw(pixels: &[u8], min_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_size;
+    let end = clear + 1;
+    let mut out = BitWriter::new(vec![]);
+
+    // The code for each string already in the table, as its prefix's code and last pixel.
+    let mut table = FxHashMap::default();
+    let mut next = end + 1;
+    let mut size = min_size as u32 + 1;
+    out.write(clear as u32, size);
+
+    let mut pixels = pixels.iter();
+    let Some(&first) = pixels.next() else {
+        out.write(end as u32, size);
+        return out.finish();
+    };
+    let mut prefix = first as u16;
+    for &pixel in pixels {
+        if let Some(&code) = table.get(&(prefix, pixel)) {
+            prefix = code;
+            continue;
+        }
+
+        out.write(prefix as u32, size);
+        if next < MAX_CODES {
+            table.insert((prefix, pixel), next);
+            if next == 1 << size {
+                size += 1;
+            }
+            next += 1;
+        } else {
+            out.write(clear as u32, size);
+            table.clear();
+            next = end + 1;
+            size = min_size as u32 + 1;
+        }
+        prefix = pixel as u16;
+    }
+    out.write(prefix as u32, size);
+    out.write(end as u32, size);
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads codes packed lowest bit first, the way GIF writes them.
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        pos: usize,
+    }
+
+    impl BitReader<'_> {
+        fn read(&mut self, len: u32) -> usize {
+            (0..len).fold(0, |value, i| {
+                let bit = (self.bytes[self.pos / 8] >> (self.pos % 8)) & 1;
+                self.pos += 1;
+                value | (bit as usize) << i
+            })
+        }
+    }
+
+    /// Decompresses the LZW codes of an image.
+    fn unlzw(data: &[u8], min_size: u8) -> Vec<u8> {
+        let clear = 1 << min_size;
+        let mut bits = BitReader {
+            bytes: data,
+            pos: 0,
+        };
+        let mut table = vec![];
+        let mut size = min_size as u32 + 1;
+        let mut prev: Option<Vec<u8>> = None;
+        let mut pixels = vec![];
+        loop {
+            let code = bits.read(size);
+            if code == clear {
+                table = (0..clear).map(|i| vec![i as u8]).collect();
+                table.extend([vec![], vec![]]);
+                size = min_size as u32 + 1;
+                prev = None;
+                continue;
+            }
+            if code == clear + 1 {
+                return pixels;
+            }
+
+            let entry = match (table.get(code), &prev) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(prev)) if code == table.len() => [&prev[..], &prev[..1]].concat(),
+                _ => panic!("code {} isn't in the table", code),
+            };
+            if let Some(prev) = prev {
+                if table.len() < MAX_CODES as usize {
+                    table.push([&prev[..], &entry[..1]].concat());
+                }
+            }
+            pixels.extend_from_slice(&entry);
+            prev = Some(entry);
+            if table.len() == 1 << size && size < 12 {
+                size += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn frames_decode_to_the_pixels_drawn() {
+        let palette = (0..8).map(|i| Color(i * 30, 0, 0)).collect::<Vec<_>>();
+        // A noisy frame fills the code table, so that codes widen to 12 bits and the
+        // table is cleared.
+        let mut seed = 1u32;
+        let noise = (0..128 * 128)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) as u8 % 8
+            })
+            .collect();
+        let frames = [
+            Raster {
+                width: 128,
+                height: 128,
+                pixels: (0..128 * 128).map(|i| (i / 128 % 8) as u8).collect(),
+            },
+            Raster {
+                width: 128,
+                height: 128,
+                pixels: noise,
+            },
+        ];
+
+        let gif = encode(&frames, &palette, 10);
+        assert!(gif.starts_with(b"GIF89a"));
+        assert_eq!(gif[13..19], [0, 0, 0, 30, 0, 0]);
+        assert_eq!(gif.last(), Some(&0x3b));
+
+        // After the header, the global color table of 8 colors and the loop extension.
+        let mut pos = 13 + 8 * 3 + 19;
+        for frame in frames.iter() {
+            assert_eq!(gif[pos..pos + 4], [0x21, 0xf9, 0x04, 0x00]);
+            pos += 8;
+            assert_eq!(gif[pos], 0x2c);
+            pos += 10;
+            let min_size = gif[pos];
+            pos += 1;
+
+            let mut data = vec![];
+            while gif[pos] != 0 {
+                let len = gif[pos] as usize;
+                data.extend_from_slice(&gif[pos + 1..pos + 1 + len]);
+                pos += 1 + len;
+            }
+            pos += 1;
+            assert_eq!(unlzw(&data, min_size), frame.pixels);
+        }
+        assert_eq!(pos, gif.len() - 1);
+    }
+}
@@ -0,0 +1,97 @@
+use super::{Color, Scene, Theme, Tile};
+
+/// The theme's colors in the order used as palette indices.
+pub(super) fn palette(theme: &Theme) -> [Color; 8] {
+    [
+        theme.background,
+        theme.wall,
+        theme.floor,
+        theme.target,
+        theme.box_color,
+        theme.box_on_target,
+        theme.player,
+        theme.path,
+    ]
+}
+
+const BACKGROUND: u8 = 0;
+const WALL: u8 = 1;
+const FLOOR: u8 = 2;
+const TARGET: u8 = 3;
+const BOX: u8 = 4;
+const BOX_ON_TARGET: u8 = 5;
+const PLAYER: u8 = 6;
+
+/// A picture of a scene, as an index into `palette` for each pixel, row by row.
+pub(super) struct Raster {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Raster {
+    /// Draws `scene` the same way as the SVG, with squares `tile` pixels across.
+    pub fn new(scene: &Scene, tile: usize) -> Self {
+        let (width, height) = (scene.width() * tile, scene.height() * tile);
+        let mut raster = Self {
+            width,
+            height,
+            pixels: vec![BACKGROUND; width * height],
+        };
+
+        let t = tile as f64;
+        for pos in scene.positions() {
+            let (x0, y0) = (pos.x() * tile, pos.y() * tile);
+            let color = match scene.tile(pos) {
+                Tile::Outside => continue,
+                Tile::Wall => WALL,
+                Tile::Floor | Tile::Target => FLOOR,
+            };
+            raster.fill(x0, y0, tile, |_, _| Some(color));
+
+            let on_target = scene.tile(pos) == Tile::Target;
+            if on_target {
+                raster.fill(x0, y0, tile, |x, y| {
+                    in_circle(x, y, t, t / 5.0).then_some(TARGET)
+                });
+            }
+            if scene.has_box(pos) {
+                let fill = if on_target { BOX_ON_TARGET } else { BOX };
+                let (lo, hi) = (t / 10.0, t * 0.9);
+                raster.fill(x0, y0, tile, |x, y| {
+                    let inside = |v: f64| (lo..hi).contains(&v);
+                    let edge = |v: f64| v < lo + 1.0 || v >= hi - 1.0;
+                    match (inside(x), inside(y)) {
+                        (true, true) if edge(x) || edge(y) => Some(WALL),
+                        (true, true) => Some(fill),
+                        _ => None,
+                    }
+                });
+            }
+            if scene.player() == pos {
+                raster.fill(x0, y0, tile, |x, y| {
+                    in_circle(x, y, t, t * 0.35).then_some(PLAYER)
+                });
+            }
+        }
+        raster
+    }
+
+    /// Colors the pixels of the square at (`x0`, `y0`) for which `color` returns a color,
+    /// given the position of the middle of the pixel within the square.
+    fn fill(&mut self, x0: usize, y0: usize, tile: usize, color: impl Fn(f64, f64) -> Option<u8>) {
+        for y in 0..tile {
+            for x in 0..tile {
+                if let Some(color) = color(x as f64 + 0.5, y as f64 + 0.5) {
+                    self.pixels[(y0 + y) * self.width + x0 + x] = color;
+                }
+            }
+        }
+    }
+}
+
+/// Returns true if (`x`, `y`) is within `r` of the middle of a square `t` across.
+fn in_circle(x: f64, y: f64, t: f64, r: f64) -> bool {
+    let (dx, dy) = (x - t / 2.0, y - t / 2.0);
+    dx * dx + dy * dy <= r * r
+}
@@ -0,0 +1,115 @@
+use std::fmt::Write;
+
+use super::{Scene, Theme, Tile};
+use crate::question::Position;
+
+/// Returns `scene` as an SVG image, with squares `tile` pixels across.
+pub fn to_svg(scene: &Scene, theme: &Theme, tile: usize) -> String {
+    to_svg_with_paths(scene, theme, tile, &[])
+}
+
+/// Like `to_svg`, with `paths` drawn over the top as arrows, such as those from `push_paths`.
+pub fn to_svg_with_paths(
+    scene: &Scene,
+    theme: &Theme,
+    tile: usize,
+    paths: &[Vec<Position>],
+) -> String {
+    // Writing to a `String` never fails, so the results of `write!` are ignored.
+    let mut svg = String::new();
+    let (width, height) = (scene.width() * tile, scene.height() * tile);
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{}" height="{}" fill="{}"/>"#,
+        width, height, theme.background
+    );
+
+    let t = tile as f64;
+    for pos in scene.positions() {
+        let (x, y) = ((pos.x() * tile) as f64, (pos.y() * tile) as f64);
+        let fill = match scene.tile(pos) {
+            Tile::Outside => continue,
+            Tile::Wall => theme.wall,
+            Tile::Floor | Tile::Target => theme.floor,
+        };
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+            x, y, t, t, fill
+        );
+
+        let on_target = scene.tile(pos) == Tile::Target;
+        if on_target {
+            let _ = writeln!(
+                svg,
+                r#"<circle class="target" cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+                x + t / 2.0,
+                y + t / 2.0,
+                t / 5.0,
+                theme.target
+            );
+        }
+        if scene.has_box(pos) {
+            let fill = if on_target {
+                theme.box_on_target
+            } else {
+                theme.box_color
+            };
+            let _ = writeln!(
+                svg,
+                r#"<rect class="box" x="{}" y="{}" width="{}" height="{}" rx="{}" fill="{}" stroke="{}"/>"#,
+                x + t / 10.0,
+                y + t / 10.0,
+                t * 0.8,
+                t * 0.8,
+                t / 8.0,
+                fill,
+                theme.wall
+            );
+        }
+        if scene.player() == pos {
+            let _ = writeln!(
+                svg,
+                r#"<circle class="player" cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+                x + t / 2.0,
+                y + t / 2.0,
+                t * 0.35,
+                theme.player
+            );
+        }
+    }
+
+    if !paths.is_empty() {
+        let _ = writeln!(
+            svg,
+            r#"<defs><marker id="arrow" viewBox="0 0 10 10" refX="5" refY="5" markerWidth="4" markerHeight="4" orient="auto"><path d="M0,0 L10,5 L0,10 z" fill="{}"/></marker></defs>"#,
+            theme.path
+        );
+    }
+    for path in paths {
+        let centre = |pos: &Position| {
+            format!(
+                "{},{}",
+                pos.x() as f64 * t + t / 2.0,
+                pos.y() as f64 * t + t / 2.0
+            )
+        };
+        let points = path.iter().map(centre).collect::<Vec<_>>().join(" ");
+        let _ = writeln!(
+            svg,
+            r#"<polyline class="path" points="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round" marker-end="url(#arrow)" opacity="0.8"/>"#,
+            points,
+            theme.path,
+            t / 10.0
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
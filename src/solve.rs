@@ -1,34 +1,269 @@
 // #![allow(unused_imports, dead_code)]
 // #![allow(warnings)]
 
-mod board;
+pub(crate) mod board;
+pub(crate) mod bounds;
 mod deadlock;
+mod difficulty;
 mod directions;
-mod puller;
+mod game;
+mod hint;
+mod optimizer;
+#[cfg(test)]
+mod properties;
+pub(crate) mod puller;
 mod puzzle;
+mod record;
+mod solution;
 mod solver;
 mod squares;
 
-use crate::question::{Question, QuestionCollection};
-use solver::Solver;
+use rustc_hash::FxHashSet;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-pub fn solve_collection(questions: &QuestionCollection) {
-    for question in questions.iter() {
-        solve_puzzle(question);
+use crate::question::{Question, QuestionCollection, Square};
+use board::Board;
+use bounds::LowerBound;
+use optimizer::Optimizer;
+use puzzle::Puzzle;
+use squares::Flags;
+
+pub use deadlock::Deadlock;
+pub use difficulty::{Difficulty, RateError, Tier};
+pub use directions::Dir;
+pub use game::{Game, IllegalMove, StepOutcome};
+pub use hint::Hint;
+pub use optimizer::{Metric, OptimizeConfig};
+pub use record::{Record, RecordFormat};
+pub use solution::{Move, ReplayError, Solution};
+pub use solver::{Algorithm, SolveStatus, SolverConfig};
+
+/// The outcome of solving a single level.
+#[derive(Debug, Clone)]
+pub struct SolveResult {
+    pub status: SolveStatus,
+    pub solution: Option<Solution>,
+    /// The number of positions that were taken off the queue and expanded.
+    pub expanded: usize,
+    /// The number of distinct positions that were seen.
+    pub visited: usize,
+    /// An estimate of the most bytes the search used at once, see `Solver::peak_memory`.
+    pub peak_memory: usize,
+    pub elapsed: Duration,
+}
+
+/// Solves every level of `questions` using up to `threads` threads, calling
+/// `on_result` with the index of each level in order.
+///
+/// Levels may finish in any order, so each result is held back until all the
+/// levels before it are done.
+pub fn solve_collection<F>(
+    questions: &QuestionCollection,
+    config: &SolverConfig,
+    threads: usize,
+    mut on_result: F,
+) where
+    F: FnMut(usize, SolveResult),
+{
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, questions.len().max(1)) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let idx = next.fetch_add(1, Ordering::Relaxed);
+                if idx >= questions.len() {
+                    break;
+                }
+                let result = solve_puzzle(&questions[idx], config);
+                if sender.send((idx, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut results = vec![None; questions.len()];
+        let mut done = 0;
+        for (idx, result) in receiver {
+            results[idx] = Some(result);
+            while let Some(result) = results.get_mut(done).and_then(Option::take) {
+                on_result(done, result);
+                done += 1;
+            }
+        }
+    });
+}
+
+pub fn solve_puzzle(question: &Question, config: &SolverConfig) -> SolveResult {
+    solve_from(&Game::new(question), config)
+}
+
+/// Solves the level from the position `game` is in, rather than from the start.
+/// The solution starts from that position too.
+pub fn solve_from(game: &Game, config: &SolverConfig) -> SolveResult {
+    let start = Instant::now();
+    let mut solver = game.solver(config);
+    let (mut status, moves) = solver.solve();
+
+    let solution = moves.and_then(|moves| {
+        let solution = replay_pushes(game, moves);
+        debug_assert!(solution.is_some(), "the solver produced an illegal move");
+        solution
+    });
+    if status == SolveStatus::Solved && solution.is_none() {
+        status = SolveStatus::Failed;
+    }
+    SolveResult {
+        status,
+        solution,
+        expanded: solver.expanded(),
+        visited: solver.visited(),
+        peak_memory: solver.peak_memory(),
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Plays the pushes of the solver's `moves` from `game`, walking to each one the
+/// shortest way, as the solver walks the player to the top left after every push.
+///
+/// Returns `None` if one of the moves can't be made.
+fn replay_pushes(game: &Game, moves: Vec<Dir>) -> Option<Solution> {
+    let mut shadow = game.clone();
+    let mut replay = game.clone();
+    for dir in moves {
+        let pos = shadow.player_pos();
+        match shadow.step(dir) {
+            Ok(StepOutcome::Pushed) => {
+                if !replay.walk_to(pos) || replay.step(dir) != Ok(StepOutcome::Pushed) {
+                    return None;
+                }
+            }
+            Ok(StepOutcome::Walked) => (),
+            _ => return None,
+        }
+    }
+    Some(Solution::new(
+        replay.history()[game.move_count()..].to_vec(),
+    ))
+}
+
+/// Shortens `solution` by searching for shortcuts around it, as measured by `config.metric`.
+///
+/// Returns an error if `solution` does not solve `question`.
+pub fn optimize_solution(
+    question: &Question,
+    solution: &Solution,
+    config: &OptimizeConfig,
+) -> Result<Solution, ReplayError> {
+    solution.verify(question)?;
+
+    let optimizer = Optimizer::new(create_puzzle(question), config);
+    Ok(optimizer.optimize(solution))
+}
+
+/// Returns a number of pushes that solving `question` needs at least, or `None` if
+/// one of its boxes can't reach any target.
+pub fn push_lower_bound(question: &Question) -> Option<usize> {
+    let (board, _, boxes) = create_board(question);
+    LowerBound::new(&board).estimate(boxes)
+}
+
+/// Converts `question` into a `Board`, the player's start position and the box positions.
+pub(crate) fn create_board(question: &Question) -> (Board, usize, FxHashSet<usize>) {
+    let (width, height) = (question.width(), question.height());
+    let mut grid = Vec::with_capacity(width * height);
+    for row in question.rows() {
+        for sq in row {
+            grid.push(match *sq {
+                Square::Wall => Flags::WALL,
+                Square::Space => Flags::SPACE,
+            })
+        }
     }
+
+    let start = question.start().to_usize(width);
+
+    // Sorted first, so that the sets, and anything that goes through them in order like
+    // the solver, come out the same every time for the same level.
+    let mapper = |it: &std::collections::HashSet<crate::question::Position>| -> FxHashSet<usize> {
+        let mut positions = it.iter().map(|p| p.to_usize(width)).collect::<Vec<_>>();
+        positions.sort_unstable();
+        positions.into_iter().collect::<FxHashSet<_>>()
+    };
+
+    let boxes = mapper(question.boxes());
+    let targets = mapper(question.targets());
+
+    (Board::new(width, height, grid, targets), start, boxes)
 }
 
-pub fn solve_puzzle(question: &Question) {
-    // let (board, start, boxes) = create_board(question);
-    // let puzzle = Puzzle::new(&board, start, boxes);
+/// Creates the starting `Puzzle` for `question`.
+fn create_puzzle(question: &Question) -> Puzzle {
+    let (board, start, boxes) = create_board(question);
+    let mut puzzle = Puzzle::new(Rc::new(board), start, boxes);
+    puzzle.update_movable_positions();
+    puzzle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::question::Symmetry;
+    use std::str::FromStr;
+
+    #[test]
+    fn optimizer_removes_detours() {
+        let question = Question::from_str("#######\n#@ $ .#\n#######").unwrap();
+        let solution = Solution::from_str("rlrRR").unwrap();
+
+        let optimized =
+            optimize_solution(&question, &solution, &OptimizeConfig::default()).unwrap();
+        assert_eq!(optimized.to_string(), "rRR");
+        assert!(optimized.verify(&question).is_ok());
+    }
 
-    // println!("\n{}", "-".repeat(50));
-    // println!("Puzzle:\n{}", puzzle);
-    // println!("moves: {:?}", puzzle.moves());
-    // println!("movable_positions:\n{}", puzzle.view_movable_positions());
-    // println!("valid positions:\n{}", puzzle.view_valid_positions());
+    #[test]
+    fn solves_every_orientation() {
+        let question = Question::from_str(" ######\n #    #\n #@$  #\n ### .#\n   ####").unwrap();
+        let solution = Solution::from_str("RRurD").unwrap();
 
-    // solver::solve(puzzle);
-    let mut solver = Solver::from(question);
-    solver.solve();
+        for symmetry in Symmetry::iter() {
+            let transformed = question.transform(symmetry);
+            assert_eq!(solution.transform(symmetry).verify(&transformed), Ok(()));
+
+            for algorithm in [Algorithm::Bfs, Algorithm::AStar, Algorithm::Greedy] {
+                let config = SolverConfig {
+                    algorithm,
+                    ..SolverConfig::default()
+                };
+                let result = solve_puzzle(&transformed, &config);
+                assert_eq!(result.status, SolveStatus::Solved);
+                let found = result.solution.expect("no solution found");
+                assert_eq!(found.verify(&transformed), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn astar_finds_the_fewest_pushes() {
+        // The first way the search finds to some positions here isn't the shortest.
+        let question = Question::from_str(
+            "#####\n#   ####\n# @    #\n## #   #\n#   .  #\n#.$  $ #\n#  $.###\n######",
+        )
+        .unwrap();
+        let config = SolverConfig {
+            algorithm: Algorithm::AStar,
+            ..SolverConfig::default()
+        };
+        let solution = solve_puzzle(&question, &config).solution.unwrap();
+        assert_eq!(solution.verify(&question), Ok(()));
+        assert_eq!(solution.push_count(), 6);
+    }
 }
@@ -54,7 +54,11 @@ impl Board {
         for &pos in self.targets.iter() {
             grid[pos] = if boxes.contains(&pos) { "*" } else { "." }
         }
-        grid[player_pos] = "@";
+        grid[player_pos] = if self.targets.contains(&player_pos) {
+            "+"
+        } else {
+            "@"
+        };
 
         grid.chunks_exact(self.width)
             .map(|row| row.to_vec())
@@ -90,13 +94,13 @@ impl Board {
         Some(pos + self.width)
     }
     pub fn east(&self, pos: usize) -> Option<usize> {
-        if (pos + 1) % self.width == 0 {
+        if (pos + 1).is_multiple_of(self.width) {
             return None;
         }
         Some(pos + 1)
     }
     pub fn west(&self, pos: usize) -> Option<usize> {
-        if pos % self.width == 0 {
+        if pos.is_multiple_of(self.width) {
             return None;
         }
         Some(pos - 1)
@@ -0,0 +1,34 @@
+use super::board::Board;
+use super::puller::Puller;
+
+/// Estimates how many pushes are still needed to solve a position.
+pub struct LowerBound {
+    /// The number of pushes needed to get a box from each square to each target,
+    /// ignoring all other boxes. `None` if it can't be done.
+    distances: Vec<Vec<Option<usize>>>,
+}
+
+impl LowerBound {
+    pub fn new(board: &Board) -> Self {
+        let puller = Puller::new(board.clone());
+        let distances = board
+            .targets
+            .iter()
+            .map(|&target| puller.find_push_distances_from(target))
+            .collect();
+        Self { distances }
+    }
+
+    /// Returns the fewest pushes needed to get the box on `pos` to any target.
+    pub fn box_distance(&self, pos: usize) -> Option<usize> {
+        self.distances.iter().filter_map(|dists| dists[pos]).min()
+    }
+
+    /// Returns a number of pushes that solving the position needs at least, or `None`
+    /// if one of the boxes can't reach any target.
+    ///
+    /// Every box needs at least as many pushes as it takes to reach its closest target.
+    pub fn estimate(&self, boxes: impl IntoIterator<Item = usize>) -> Option<usize> {
+        boxes.into_iter().map(|pos| self.box_distance(pos)).sum()
+    }
+}
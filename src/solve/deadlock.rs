@@ -1,11 +1,80 @@
 use rustc_hash::FxHashSet;
+use std::fmt;
 
+use super::puller::Puller;
 use super::{board::Board, directions::Dir};
+use crate::question::Position;
 
 pub struct Detector {
     board: Board,
 }
 
+/// Why a position can't be solved any more.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Deadlock {
+    /// The box is on a square from which it can never be pushed onto a target.
+    DeadSquare(Position),
+    /// These boxes can never move again, and at least one of them is off target.
+    Freeze(Vec<Position>),
+    /// The player can never get into `squares` again, because the boxes around it
+    /// can never move, and there is a target inside that no box can reach.
+    Corral {
+        squares: Vec<Position>,
+        boxes: Vec<Position>,
+    },
+    /// These boxes can't all reach different targets.
+    Matching(Vec<Position>),
+}
+
+impl Deadlock {
+    /// Returns the boxes involved in the deadlock.
+    pub fn boxes(&self) -> &[Position] {
+        match self {
+            Self::DeadSquare(pos) => std::slice::from_ref(pos),
+            Self::Freeze(boxes) | Self::Matching(boxes) | Self::Corral { boxes, .. } => boxes,
+        }
+    }
+
+    /// Returns the squares involved in the deadlock, other than the boxes' squares.
+    pub fn squares(&self) -> &[Position] {
+        match self {
+            Self::Corral { squares, .. } => squares,
+            _ => &[],
+        }
+    }
+}
+
+impl fmt::Display for Deadlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |positions: &[Position]| {
+            positions
+                .iter()
+                .map(|pos| format!("({}, {})", pos.x(), pos.y()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            Self::DeadSquare(pos) => write!(
+                f,
+                "the box at ({}, {}) can never reach a target",
+                pos.x(),
+                pos.y()
+            ),
+            Self::Freeze(boxes) => write!(f, "the boxes at {} can never move", list(boxes)),
+            Self::Corral { boxes, .. } => write!(
+                f,
+                "the boxes at {} seal off a target that no box can reach",
+                list(boxes)
+            ),
+            Self::Matching(boxes) => write!(
+                f,
+                "the boxes at {} can't all reach a target of their own",
+                list(boxes)
+            ),
+        }
+    }
+}
+
 impl Detector {
     pub fn new(board: &Board) -> Self {
         Self {
@@ -13,29 +82,243 @@ impl Detector {
         }
     }
 
-    /// Returns `true` if it is sure that the position is deadlocked.
+    /// Returns why the position is deadlocked if it is sure that it is.
+    ///
+    /// Only looks at `last_moved`, so is quick enough to run on every push. Finds
+    /// `DeadSquare` and `Freeze` deadlocks.
     pub fn is_deadlocked(
         &self,
-        player_pos: usize,
+        _player_pos: usize,
         boxes: &FxHashSet<usize>,
         last_moved: usize,
-    ) -> bool {
-        !self.board.targets.contains(&last_moved)
-            && (self.is_dead_square(boxes, last_moved) || self.check_box_blocked(boxes, last_moved))
+    ) -> Option<Deadlock> {
+        if self.board.targets.contains(&last_moved) {
+            return None;
+        }
+        if self.is_dead_square(last_moved) {
+            return Some(Deadlock::DeadSquare(self.position(last_moved)));
+        }
+        self.find_freeze(boxes, last_moved)
+    }
+
+    /// Looks at the whole position, and explains why it is deadlocked if it is sure that it is.
+    ///
+    /// Slower than `is_deadlocked`, and also finds `Corral` and `Matching` deadlocks.
+    pub fn explain(&self, player_pos: usize, boxes: &FxHashSet<usize>) -> Option<Deadlock> {
+        let mut sorted = boxes.iter().copied().collect::<Vec<_>>();
+        sorted.sort_unstable();
+        let off_target = sorted
+            .iter()
+            .copied()
+            .filter(|pos| !self.board.targets.contains(pos))
+            .collect::<Vec<_>>();
+
+        if let Some(&pos) = off_target.iter().find(|&&pos| self.is_dead_square(pos)) {
+            return Some(Deadlock::DeadSquare(self.position(pos)));
+        }
+
+        off_target
+            .iter()
+            .find_map(|&pos| self.find_freeze(boxes, pos))
+            .or_else(|| self.find_corral(player_pos, boxes))
+            .or_else(|| self.find_unmatched(&sorted))
+    }
+
+    fn position(&self, pos: usize) -> Position {
+        Position::from_usize(pos, self.board.width)
+    }
+
+    /// Converts `positions` into sorted `Position`s.
+    fn positions(&self, positions: impl IntoIterator<Item = usize>) -> Vec<Position> {
+        let mut positions = positions.into_iter().collect::<Vec<_>>();
+        positions.sort_unstable();
+        positions
+            .into_iter()
+            .map(|pos| self.position(pos))
+            .collect()
     }
 }
 
 impl Detector {
-    fn is_dead_square(&self, boxes: &FxHashSet<usize>, last_moved: usize) -> bool {
+    fn is_dead_square(&self, last_moved: usize) -> bool {
         !self.board.square_at(last_moved).is_valid()
     }
+
+    /// Returns the squares that can be walked on, starting from `start`, without
+    /// going through walls, boxes or `outside`.
+    fn flood_fill(
+        &self,
+        start: usize,
+        boxes: &FxHashSet<usize>,
+        outside: &FxHashSet<usize>,
+    ) -> FxHashSet<usize> {
+        let mut area = FxHashSet::from_iter([start]);
+        let mut bag = vec![start];
+        while let Some(current) = bag.pop() {
+            for pos in self.board.borders(current) {
+                if self.board.square_at(pos).is_space()
+                    && !boxes.contains(&pos)
+                    && !outside.contains(&pos)
+                    && area.insert(pos)
+                {
+                    bag.push(pos);
+                }
+            }
+        }
+        area
+    }
+
+    /// Finds an area the player can't reach which holds an empty target, and is
+    /// closed off by boxes which can never move. No box can ever get onto that target.
+    fn find_corral(&self, player_pos: usize, boxes: &FxHashSet<usize>) -> Option<Deadlock> {
+        let reachable = self.flood_fill(player_pos, boxes, &FxHashSet::default());
+
+        let mut seen = reachable.clone();
+        let mut targets = self.board.targets.iter().copied().collect::<Vec<_>>();
+        targets.sort_unstable();
+        for target in targets {
+            if seen.contains(&target) || boxes.contains(&target) {
+                continue;
+            }
+
+            let corral = self.flood_fill(target, boxes, &reachable);
+            seen.extend(corral.iter().copied());
+
+            let sealing = corral
+                .iter()
+                .flat_map(|&pos| self.board.borders(pos))
+                .filter(|pos| boxes.contains(pos))
+                .collect::<FxHashSet<_>>();
+            if sealing
+                .iter()
+                .all(|&pos| self.check_box_blocked(boxes, pos))
+            {
+                return Some(Deadlock::Corral {
+                    squares: self.positions(corral),
+                    boxes: self.positions(sealing),
+                });
+            }
+        }
+        None
+    }
+
+    /// Matches boxes to targets they could reach on their own, and returns the boxes
+    /// left over if there is no way to give every box its own target.
+    fn find_unmatched(&self, boxes: &[usize]) -> Option<Deadlock> {
+        let puller = Puller::new(self.board.clone());
+        let targets = self.board.targets.iter().copied().collect::<Vec<_>>();
+        let reachable = targets
+            .iter()
+            .map(|&target| puller.find_push_distances_from(target))
+            .collect::<Vec<_>>();
+        let edges = boxes
+            .iter()
+            .map(|&pos| {
+                (0..targets.len())
+                    .filter(|&t| reachable[t][pos].is_some())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // The box matched with each target, found by augmenting paths.
+        let mut matched = vec![None; targets.len()];
+        let mut unmatched = vec![];
+        for (b, &pos) in boxes.iter().enumerate() {
+            let mut tried = vec![false; targets.len()];
+            if !augment(b, &edges, &mut matched, &mut tried) {
+                unmatched.push(pos);
+            }
+        }
+
+        (!unmatched.is_empty()).then(|| Deadlock::Matching(self.positions(unmatched)))
+    }
+}
+
+/// Tries to match box `b` with a target, moving other boxes to different targets if needed.
+fn augment(
+    b: usize,
+    edges: &[Vec<usize>],
+    matched: &mut [Option<usize>],
+    tried: &mut [bool],
+) -> bool {
+    for &t in edges[b].iter() {
+        if tried[t] {
+            continue;
+        }
+        tried[t] = true;
+        if matched[t].is_none_or(|other| augment(other, edges, matched, tried)) {
+            matched[t] = Some(b);
+            return true;
+        }
+    }
+    false
 }
 
 impl Detector {
-    fn check_box_blocked(&self, boxes: &FxHashSet<usize>, box_pos: usize) -> bool {
+    /// Returns the boxes of `boxes` that are blocked both across and up and down, by
+    /// walls or by the other boxes returned, so that none of them can ever move.
+    fn frozen(&self, mut boxes: FxHashSet<usize>) -> FxHashSet<usize> {
+        loop {
+            let loose = boxes
+                .iter()
+                .copied()
+                .filter(|&pos| {
+                    !self.is_blocked_by(&boxes, pos, Dir::North)
+                        || !self.is_blocked_by(&boxes, pos, Dir::East)
+                })
+                .collect::<Vec<_>>();
+            if loose.is_empty() {
+                return boxes;
+            }
+            for pos in loose {
+                boxes.remove(&pos);
+            }
+        }
+    }
+
+    /// Returns true if the box at `box_pos` can't be pushed either way along `dir`,
+    /// or only onto a dead square, with `boxes` the only boxes that can block it.
+    fn is_blocked_by(&self, boxes: &FxHashSet<usize>, box_pos: usize, dir: Dir) -> bool {
+        let (Some(a), Some(b)) = (
+            self.board.step(box_pos, dir, 1),
+            self.board.step(box_pos, dir.opposite(), 1),
+        ) else {
+            return true;
+        };
+        let blocks = |pos: usize| self.board.square_at(pos).is_wall() || boxes.contains(&pos);
+        blocks(a)
+            || blocks(b)
+            || !self.board.square_at(a).is_valid() && !self.board.square_at(b).is_valid()
+    }
+
+    /// Returns the boxes that can never move if the box at `box_pos` is one of them,
+    /// and at least one of them is off target.
+    fn find_freeze(&self, boxes: &FxHashSet<usize>, box_pos: usize) -> Option<Deadlock> {
         let mut considered = FxHashSet::default();
-        self.check_box_blocked_direction(boxes, box_pos, &mut considered, Dir::North)
-            && self.check_box_blocked_direction(boxes, box_pos, &mut considered, Dir::East)
+        if !self.check_box_blocked_with(boxes, box_pos, &mut considered) {
+            return None;
+        }
+        // Not every box looked at on the way is frozen itself.
+        let frozen = self.frozen(considered);
+        frozen
+            .iter()
+            .any(|pos| !self.board.targets.contains(pos))
+            .then(|| Deadlock::Freeze(self.positions(frozen)))
+    }
+
+    fn check_box_blocked(&self, boxes: &FxHashSet<usize>, box_pos: usize) -> bool {
+        self.check_box_blocked_with(boxes, box_pos, &mut FxHashSet::default())
+    }
+
+    /// Like `check_box_blocked`, leaving the boxes that were looked at in `considered`.
+    fn check_box_blocked_with(
+        &self,
+        boxes: &FxHashSet<usize>,
+        box_pos: usize,
+        considered: &mut FxHashSet<usize>,
+    ) -> bool {
+        self.check_box_blocked_direction(boxes, box_pos, considered, Dir::North)
+            && self.check_box_blocked_direction(boxes, box_pos, considered, Dir::East)
     }
 
     fn check_box_blocked_direction(
@@ -46,8 +329,13 @@ impl Detector {
         dir: Dir,
     ) -> bool {
         considered.insert(box_pos);
-        let a = self.board.step(box_pos, dir, 1).unwrap();
-        let b = self.board.step(box_pos, dir.opposite(), 1).unwrap();
+        // The edge of the level blocks a box just like a wall.
+        let (Some(a), Some(b)) = (
+            self.board.step(box_pos, dir, 1),
+            self.board.step(box_pos, dir.opposite(), 1),
+        ) else {
+            return true;
+        };
 
         self.board.square_at(a).is_wall()
             || self.board.square_at(b).is_wall()
@@ -60,3 +348,60 @@ impl Detector {
                 && self.check_box_blocked_direction(boxes, b, considered, dir.rotation())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::question::Question;
+    use crate::solve::Game;
+    use std::str::FromStr;
+
+    fn explain(level: &str) -> Option<Deadlock> {
+        Game::new(&Question::from_str(level).unwrap()).deadlock()
+    }
+
+    #[test]
+    fn explains_deadlocks() {
+        let square = |x, y| Position::new(x, y);
+
+        assert_eq!(explain("#######\n#@$ .#\n#######"), None);
+        assert_eq!(
+            explain("#####\n#$ .#\n#@ .#\n# $ #\n#####"),
+            Some(Deadlock::DeadSquare(square(1, 1)))
+        );
+        assert_eq!(
+            explain("########\n#      #\n# $$ . #\n# $$ . #\n#@   ..#\n########"),
+            Some(Deadlock::Freeze(vec![
+                square(2, 2),
+                square(3, 2),
+                square(2, 3),
+                square(3, 3)
+            ]))
+        );
+        // The box above is looked at, but can still be pushed across.
+        assert_eq!(
+            explain("#######\n#     #\n# $ . #\n##$ . #\n##$  .#\n#@    #\n#######"),
+            Some(Deadlock::Freeze(vec![square(2, 3), square(2, 4)]))
+        );
+        assert_eq!(
+            explain("#######\n#.$ $ #\n#     #\n#    .#\n#@    #\n#######"),
+            Some(Deadlock::Matching(vec![square(4, 1)]))
+        );
+
+        let level = "######\n#.*#*#\n###  #\n#  $ #\n#@   #\n######";
+        let deadlock = explain(level).unwrap();
+        assert_eq!(
+            deadlock,
+            Deadlock::Corral {
+                squares: vec![square(1, 1)],
+                boxes: vec![square(2, 1)],
+            }
+        );
+
+        let game = Game::new(&Question::from_str(level).unwrap());
+        assert_eq!(
+            game.view_deadlock(&deadlock),
+            "######\n#xX#*#\n###  #\n#  $ #\n#@   #\n######"
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use super::deadlock::Detector;
+use super::directions::Dir;
+use super::solution::{ReplayError, Solution};
+use super::solver::{SolveStatus, SolverConfig};
+use super::StepOutcome;
+use crate::json;
+use crate::question::{ParseError, Question};
+
+/// Named bands of difficulty, easiest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tier {
+    Trivial,
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl Tier {
+    pub fn names() -> &'static [&'static str] {
+        &["trivial", "easy", "medium", "hard", "expert"]
+    }
+
+    /// Returns the tier for a `Difficulty::score`.
+    pub fn from_score(score: f64) -> Self {
+        match score {
+            s if s < 15.0 => Self::Trivial,
+            s if s < 35.0 => Self::Easy,
+            s if s < 70.0 => Self::Medium,
+            s if s < 120.0 => Self::Hard,
+            _ => Self::Expert,
+        }
+    }
+}
+
+impl FromStr for Tier {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "trivial" => Ok(Self::Trivial),
+            "easy" => Ok(Self::Easy),
+            "medium" => Ok(Self::Medium),
+            "hard" => Ok(Self::Hard),
+            "expert" => Ok(Self::Expert),
+            _ => Err(ParseError::Text(format!(
+                "unknown tier {:?}, expected one of {}",
+                s,
+                Self::names().join(", ")
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Tier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Trivial => "trivial",
+            Self::Easy => "easy",
+            Self::Medium => "medium",
+            Self::Hard => "hard",
+            Self::Expert => "expert",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Why a level couldn't be rated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateError {
+    /// The solver didn't find a solution, and ended like this.
+    Unsolved(SolveStatus),
+    /// The solution found doesn't solve the level when it is played.
+    Replay(ReplayError),
+}
+
+impl Error for RateError {}
+
+impl fmt::Display for RateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unsolved(status) => write!(f, "{}", status),
+            Self::Replay(err) => write!(f, "the solution found doesn't replay: {}", err),
+        }
+    }
+}
+
+/// How hard a level is, from what it takes to solve it.
+///
+/// Measured along a solution, so the numbers depend on the solution used, and the
+/// number of positions expanded on the solver's settings. Only compare ratings made
+/// the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difficulty {
+    pub moves: usize,
+    pub pushes: usize,
+    /// The number of positions the solver expanded to find the solution.
+    pub expanded: usize,
+    /// The average number of single square pushes the player could choose from
+    /// before each push of the solution.
+    pub branching: f64,
+    /// How many times a box is pushed in a different direction to its last push,
+    /// without another box being pushed in between.
+    pub direction_changes: usize,
+    /// How many times the player stops pushing one box and starts on another.
+    pub box_switches: usize,
+    /// The share of the pushes counted by `branching` that would deadlock the level.
+    pub deadlock_density: f64,
+    /// All of the above, weighted and added up. Higher is harder.
+    pub score: f64,
+    pub tier: Tier,
+}
+
+impl Difficulty {
+    /// Solves `question` with `config` and rates it along the solution found. Returns
+    /// how the search ended if no solution was found.
+    pub fn rate(question: &Question, config: &SolverConfig) -> Result<Self, RateError> {
+        let result = super::solve_puzzle(question, config);
+        let solution = result.solution.ok_or(RateError::Unsolved(result.status))?;
+        Self::measure(question, &solution, result.expanded).map_err(RateError::Replay)
+    }
+
+    /// Rates `question` along `solution`, which took `expanded` positions to find.
+    pub fn measure(
+        question: &Question,
+        solution: &Solution,
+        expanded: usize,
+    ) -> Result<Self, ReplayError> {
+        solution.verify(question)?;
+
+        let mut puzzle = super::create_puzzle(question);
+        let detector = Detector::new(puzzle.board());
+        let (mut choices, mut deadlocks) = (0, 0);
+        let (mut direction_changes, mut box_switches) = (0, 0);
+        // Where the last box pushed ended up, and which way it went.
+        let mut last_push: Option<(usize, Dir)> = None;
+
+        for m in solution.moves() {
+            if m.push {
+                for (box_pos, dir) in single_pushes(&puzzle) {
+                    let to = puzzle.board().step(box_pos, dir, 1).unwrap();
+                    let mut boxes = puzzle.boxes.clone();
+                    boxes.remove(&box_pos);
+                    boxes.insert(to);
+                    choices += 1;
+                    if detector.is_deadlocked(box_pos, &boxes, to).is_some() {
+                        deadlocks += 1;
+                    }
+                }
+
+                let box_pos = puzzle.board().step(puzzle.player_pos, m.dir, 1).unwrap();
+                match last_push {
+                    Some((pos, dir)) if pos == box_pos && dir != m.dir => direction_changes += 1,
+                    Some((pos, _)) if pos != box_pos => box_switches += 1,
+                    _ => (),
+                }
+                last_push = puzzle.board().step(box_pos, m.dir, 1).map(|to| (to, m.dir));
+            }
+            let outcome = puzzle.step(m.dir);
+            debug_assert!(matches!(
+                outcome,
+                Ok(StepOutcome::Walked | StepOutcome::Pushed)
+            ));
+        }
+
+        let pushes = solution.push_count();
+        let per_push = |n: usize| n as f64 / pushes.max(1) as f64;
+        let branching = per_push(choices);
+        let deadlock_density = if choices == 0 {
+            0.0
+        } else {
+            deadlocks as f64 / choices as f64
+        };
+
+        let score = pushes as f64 * 0.5
+            + box_switches as f64 * 2.0
+            + direction_changes as f64
+            + (expanded as f64 + 1.0).log2() * 3.0
+            + branching
+            + deadlock_density * 20.0;
+
+        Ok(Self {
+            moves: solution.move_count(),
+            pushes,
+            expanded,
+            branching,
+            direction_changes,
+            box_switches,
+            deadlock_density,
+            score,
+            tier: Tier::from_score(score),
+        })
+    }
+
+    /// Returns the rating as a JSON object.
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .string("tier", &self.tier.to_string())
+            .number("score", format!("{:.2}", self.score))
+            .number("moves", self.moves)
+            .number("pushes", self.pushes)
+            .number("expanded", self.expanded)
+            .number("branching", format!("{:.2}", self.branching))
+            .number("direction_changes", self.direction_changes)
+            .number("box_switches", self.box_switches)
+            .number("deadlock_density", format!("{:.3}", self.deadlock_density))
+            .to_string()
+    }
+}
+
+/// Returns every push of a box by one square that the player can walk to and make.
+fn single_pushes(puzzle: &super::puzzle::Puzzle) -> Vec<(usize, Dir)> {
+    let board = puzzle.board();
+    let free = |pos: usize| board.square_at(pos).is_space() && !puzzle.boxes.contains(&pos);
+    let mut pushes = vec![];
+    for &box_pos in puzzle.boxes.iter() {
+        for dir in Dir::iter() {
+            let from = board.step(box_pos, dir.opposite(), 1);
+            let to = board.step(box_pos, dir, 1);
+            if from.is_some_and(|pos| puzzle.movable_positions.contains(&pos))
+                && to.is_some_and(free)
+            {
+                pushes.push((box_pos, dir));
+            }
+        }
+    }
+    pushes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn difficulty() {
+        let question = Question::from_str("#######\n#     #\n#@$  .#\n#  $ .#\n#######").unwrap();
+        let solution = Solution::from_str("RRRlldRR").unwrap();
+        let difficulty = Difficulty::measure(&question, &solution, 10).unwrap();
+
+        assert_eq!((difficulty.moves, difficulty.pushes), (8, 5));
+        assert_eq!(difficulty.box_switches, 1);
+        assert_eq!(difficulty.direction_changes, 0);
+        assert_eq!(difficulty.tier, Tier::Easy);
+        // Pushing a box up against the top wall, where there are no targets, is a deadlock.
+        assert!(difficulty.deadlock_density > 0.0, "{:?}", difficulty);
+
+        let turn = Difficulty::measure(
+            &Question::from_str("######\n#    #\n#@$  #\n#   .#\n######").unwrap(),
+            &Solution::from_str("RRurD").unwrap(),
+            1,
+        )
+        .unwrap();
+        assert_eq!((turn.direction_changes, turn.box_switches), (1, 0));
+        assert!(turn.score < difficulty.score, "{:?} {:?}", turn, difficulty);
+    }
+}
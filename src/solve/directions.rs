@@ -1,4 +1,4 @@
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Dir {
     North,
     East,
@@ -30,6 +30,33 @@ impl Dir {
             Dir::West => Dir::North,
         }
     }
+
+    /// Returns the LURD character for a move in this direction. Pushes are uppercase.
+    pub fn to_lurd(&self, push: bool) -> char {
+        let c = match self {
+            Dir::North => 'u',
+            Dir::East => 'r',
+            Dir::South => 'd',
+            Dir::West => 'l',
+        };
+        if push {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        }
+    }
+
+    /// Parses a LURD character into its direction and whether it is a push.
+    pub fn from_lurd(c: char) -> Option<(Self, bool)> {
+        let dir = match c.to_ascii_lowercase() {
+            'u' => Dir::North,
+            'r' => Dir::East,
+            'd' => Dir::South,
+            'l' => Dir::West,
+            _ => return None,
+        };
+        Some((dir, c.is_ascii_uppercase()))
+    }
 }
 
 #[derive(Default, Clone)]
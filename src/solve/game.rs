@@ -0,0 +1,290 @@
+use std::error::Error;
+use std::fmt;
+
+use super::deadlock::{Deadlock, Detector};
+use super::puzzle::Puzzle;
+use super::solution::{Move, Solution};
+use super::solver::{Solver, SolverConfig};
+use super::{Dir, Question};
+use crate::question::Position;
+
+/// What happened when the player tried to take a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Walked,
+    Pushed,
+    /// The player walked into a wall, or a box that couldn't be pushed. Nothing moved.
+    Blocked,
+}
+
+/// A step that isn't allowed by the rules at all. Nothing moved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalMove {
+    /// The step would take the player off the edge of the level.
+    OffBoard,
+    /// The step would push a box off the edge of the level.
+    BoxOffBoard,
+}
+
+impl Error for IllegalMove {}
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OffBoard => write!(f, "the player would leave the level"),
+            Self::BoxOffBoard => write!(f, "a box would be pushed off the level"),
+        }
+    }
+}
+
+/// A level being played a step at a time, with unlimited undo and redo.
+///
+/// Unlike the solver, which moves whole boxes at once, every step is checked and
+/// nothing panics on a bad move.
+#[derive(Debug, Clone)]
+pub struct Game {
+    puzzle: Puzzle,
+    /// The metadata of the level the game started from.
+    metadata: Vec<(String, String)>,
+    history: Vec<Move>,
+    /// The moves that were undone, most recent last, so that they can be redone.
+    undone: Vec<Move>,
+}
+
+impl Game {
+    pub fn new(question: &Question) -> Self {
+        Self {
+            puzzle: super::create_puzzle(question),
+            metadata: question.metadata().to_vec(),
+            history: vec![],
+            undone: vec![],
+        }
+    }
+
+    /// Moves the player one square in `dir`, pushing a box if there is one in the way.
+    pub fn step(&mut self, dir: Dir) -> Result<StepOutcome, IllegalMove> {
+        let outcome = self.puzzle.step(dir)?;
+        let push = match outcome {
+            StepOutcome::Walked => false,
+            StepOutcome::Pushed => true,
+            StepOutcome::Blocked => return Ok(outcome),
+        };
+
+        let m = Move { dir, push };
+        self.history.push(m);
+        // Keep the redo history while the player retraces it.
+        if self.undone.last() == Some(&m) {
+            self.undone.pop();
+        } else {
+            self.undone.clear();
+        }
+        Ok(outcome)
+    }
+
+    /// Plays every move of `solution`, checking that each one walks or pushes as it says.
+    ///
+    /// Stops at the first move that doesn't, returning its index.
+    pub fn play(&mut self, solution: &Solution) -> Result<(), usize> {
+        for (idx, m) in solution.moves().iter().enumerate() {
+            let expected = if m.push {
+                StepOutcome::Pushed
+            } else {
+                StepOutcome::Walked
+            };
+            match self.step(m.dir) {
+                Ok(outcome) if outcome == expected => (),
+                Ok(StepOutcome::Walked | StepOutcome::Pushed) => {
+                    self.undo();
+                    return Err(idx);
+                }
+                _ => return Err(idx),
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes back the last move. Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(m) => {
+                self.puzzle.unstep(m.dir, m.push);
+                self.undone.push(m);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Makes the last undone move again. Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.undone.last() {
+            Some(&m) => self.step(m.dir).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Goes back to the start. Every move can still be redone.
+    pub fn restart(&mut self) {
+        while self.undo() {}
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.puzzle.is_solved()
+    }
+
+    pub fn move_count(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn push_count(&self) -> usize {
+        self.puzzle.pushes
+    }
+
+    /// Returns every move made since the start, not counting undone ones.
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    /// Returns the moves that can be redone, the next one first.
+    pub fn redo_history(&self) -> impl Iterator<Item = &Move> {
+        self.undone.iter().rev()
+    }
+
+    /// Returns the moves made so far as a solution.
+    pub fn solution(&self) -> Solution {
+        Solution::new(self.history.clone())
+    }
+
+    pub fn player(&self) -> Position {
+        Position::from_usize(self.puzzle.player_pos, self.puzzle.board().width)
+    }
+
+    /// Returns the positions of the boxes, sorted by row and then column.
+    pub fn boxes(&self) -> Vec<Position> {
+        let mut boxes = self.puzzle.boxes.iter().copied().collect::<Vec<_>>();
+        boxes.sort_unstable();
+        boxes
+            .into_iter()
+            .map(|pos| Position::from_usize(pos, self.puzzle.board().width))
+            .collect()
+    }
+
+    /// Returns the current position as a level, with the metadata of the level the
+    /// game started from, so that it can be saved and played on from later.
+    pub fn to_question(&self) -> Question {
+        let mut question = self.puzzle.to_question();
+        for (key, value) in &self.metadata {
+            question.set_metadata(key, value.clone());
+        }
+        question
+    }
+
+    /// Explains why the current position can't be solved, if it is sure that it can't.
+    pub fn deadlock(&self) -> Option<Deadlock> {
+        Detector::new(self.puzzle.board()).explain(self.puzzle.player_pos, &self.puzzle.boxes)
+    }
+
+    /// Returns the level with the boxes (`X`) and squares (`x`) involved in `deadlock` marked.
+    pub fn view_deadlock(&self, deadlock: &Deadlock) -> String {
+        self.puzzle.view_deadlock(deadlock)
+    }
+
+    pub(crate) fn player_pos(&self) -> usize {
+        self.puzzle.player_pos
+    }
+
+    /// Walks to `pos` the shortest way, without pushing any boxes. Returns false,
+    /// without moving, if there is no way there.
+    pub(crate) fn walk_to(&mut self, pos: usize) -> bool {
+        match self.puzzle.find_path(pos) {
+            Some(path) => {
+                for dir in path {
+                    self.step(dir).expect("the path walked off the board");
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns a solver that searches from the current position.
+    pub(super) fn solver(&self, config: &SolverConfig) -> Solver {
+        Solver::new(
+            self.puzzle.board().clone(),
+            self.puzzle.player_pos,
+            self.puzzle.boxes.clone(),
+            config,
+        )
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.puzzle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn undo_and_redo() {
+        let question = Question::from_str("######\n#@$ .#\n######").unwrap();
+        let mut game = Game::new(&question);
+
+        assert_eq!(game.step(Dir::West), Ok(StepOutcome::Blocked));
+        assert_eq!(game.step(Dir::East), Ok(StepOutcome::Pushed));
+        assert_eq!(game.step(Dir::East), Ok(StepOutcome::Pushed));
+        assert_eq!(game.step(Dir::East), Ok(StepOutcome::Blocked));
+        assert!(game.is_solved());
+
+        assert!(game.undo());
+        assert!(!game.is_solved());
+        game.restart();
+        assert_eq!((game.move_count(), game.push_count()), (0, 0));
+
+        assert!(game.redo() && game.redo());
+        assert!(!game.redo());
+        assert!(game.is_solved());
+        assert_eq!(game.solution().to_string(), "RR");
+    }
+
+    #[test]
+    fn illegal_moves() {
+        // A level that is open along the top and right, so the player and box can reach the edge.
+        let question = Question::from_str("#@$.\n####").unwrap();
+        let mut game = Game::new(&question);
+
+        assert_eq!(game.step(Dir::West), Ok(StepOutcome::Blocked));
+        assert_eq!(game.step(Dir::North), Err(IllegalMove::OffBoard));
+        assert_eq!(game.step(Dir::East), Ok(StepOutcome::Pushed));
+        assert_eq!(game.step(Dir::East), Err(IllegalMove::BoxOffBoard));
+        assert_eq!(game.player(), Position::new(2, 0));
+        assert_eq!(game.boxes(), vec![Position::new(3, 0)]);
+        assert_eq!(game.move_count(), 1);
+    }
+
+    #[test]
+    fn saves_the_position() {
+        let question =
+            Question::from_str("#######\n#  .  #\n#@$ $.#\n#######\nTitle: Two\nAuthor: Someone")
+                .unwrap();
+        let mut game = Game::new(&question);
+        for dir in [Dir::East, Dir::North, Dir::East] {
+            game.step(dir).unwrap();
+        }
+
+        let saved = game.to_question();
+        assert_eq!(
+            saved.to_string(),
+            "#######\n#  +  #\n#  $$.#\n#######\nTitle: Two\nAuthor: Someone"
+        );
+        assert_eq!(Question::from_str(&saved.to_string()).unwrap(), saved);
+
+        let resumed = Game::new(&saved);
+        assert_eq!(resumed.player(), game.player());
+        assert_eq!(resumed.boxes(), game.boxes());
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+
+use super::deadlock::Deadlock;
+use super::directions::Dir;
+use super::game::Game;
+use super::solution::Move;
+use super::solver::{SolveStatus, SolverConfig};
+use crate::question::Position;
+
+/// What to do next from a position part-way through a level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hint {
+    /// Every box is already on a target.
+    Solved,
+    /// The position can't be solved any more.
+    Deadlocked(Deadlock),
+    /// Walk with `moves`, the last of which pushes the box at `from` in `dir`.
+    Push {
+        moves: Vec<Move>,
+        from: Position,
+        dir: Dir,
+    },
+    /// The solver didn't find a solution from here, and stopped because of `status`.
+    NotFound(SolveStatus),
+}
+
+impl Game {
+    /// Returns the next push of a solution from the current position.
+    ///
+    /// Positions that are obviously lost are reported straight away, with the reason,
+    /// without running the solver.
+    pub fn hint(&self, config: &SolverConfig) -> Hint {
+        if self.is_solved() {
+            return Hint::Solved;
+        }
+
+        if let Some(deadlock) = self.deadlock() {
+            return Hint::Deadlocked(deadlock);
+        }
+
+        let result = super::solve_from(self, config);
+        let Some(solution) = result.solution else {
+            return Hint::NotFound(result.status);
+        };
+
+        let mut player = self.player();
+        let mut moves = vec![];
+        for &m in solution.moves() {
+            moves.push(m);
+            if m.push {
+                return Hint::Push {
+                    moves,
+                    from: player.neighbour(m.dir).unwrap(),
+                    dir: m.dir,
+                };
+            }
+            player = player.neighbour(m.dir).unwrap();
+        }
+        // A solution to an unsolved position always has a push, unless the solver is wrong.
+        Hint::NotFound(SolveStatus::Failed)
+    }
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Solved => write!(f, "already solved"),
+            Self::Deadlocked(deadlock) => write!(f, "deadlocked, {}", deadlock),
+            Self::Push { moves, from, dir } => {
+                let name = match dir {
+                    Dir::North => "up",
+                    Dir::East => "right",
+                    Dir::South => "down",
+                    Dir::West => "left",
+                };
+                let lurd = moves
+                    .iter()
+                    .map(|m| m.dir.to_lurd(m.push))
+                    .collect::<String>();
+                write!(
+                    f,
+                    "push the box at ({}, {}) {}: {}",
+                    from.x(),
+                    from.y(),
+                    name,
+                    lurd
+                )
+            }
+            Self::NotFound(status) => write!(f, "no solution found from here ({})", status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::question::Question;
+    use std::str::FromStr;
+
+    #[test]
+    fn hints() {
+        let question = Question::from_str("######\n#@ $.#\n#  $.#\n######").unwrap();
+        let mut game = Game::new(&question);
+
+        let Hint::Push { moves, from, .. } = game.hint(&SolverConfig::default()) else {
+            panic!("expected a push");
+        };
+        assert_eq!(moves.last().map(|m| m.push), Some(true));
+        assert!(game.boxes().contains(&from));
+
+        game.step(Dir::East).unwrap();
+        game.step(Dir::East).unwrap();
+        let hint = game.hint(&SolverConfig::default());
+        assert!(matches!(hint, Hint::Push { from, .. } if from == Position::new(3, 2)));
+
+        let question = Question::from_str("######\n#$  .#\n#@ $.#\n#    #\n######").unwrap();
+        let hint = Game::new(&question).hint(&SolverConfig::default());
+        assert_eq!(
+            hint,
+            Hint::Deadlocked(Deadlock::DeadSquare(Position::new(1, 1)))
+        );
+    }
+}
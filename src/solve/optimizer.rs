@@ -0,0 +1,315 @@
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::mem;
+use std::time::{Duration, Instant};
+
+use super::directions::Dir;
+use super::game::StepOutcome;
+use super::puzzle::Puzzle;
+use super::solution::{Move, Solution};
+
+/// Box positions, always kept sorted so that equal configurations compare equal.
+type Boxes = Vec<usize>;
+
+/// The boxes and the player's position.
+type State = (Boxes, usize);
+
+/// The order in which two solutions are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// Fewest moves, with ties broken by fewest pushes.
+    MovesPushes,
+    /// Fewest pushes, with ties broken by fewest moves.
+    PushesMoves,
+}
+
+impl Metric {
+    fn cost(&self, moves: usize, pushes: usize) -> (usize, usize) {
+        match self {
+            Metric::MovesPushes => (moves, pushes),
+            Metric::PushesMoves => (pushes, moves),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OptimizeConfig {
+    pub metric: Metric,
+    pub time_limit: Duration,
+    /// The most box configurations a search window may contain. Once the window
+    /// around the solution grows past this, the optimizer stops.
+    pub max_window: usize,
+}
+
+impl Default for OptimizeConfig {
+    fn default() -> Self {
+        Self {
+            metric: Metric::MovesPushes,
+            time_limit: Duration::from_secs(5),
+            max_window: 200_000,
+        }
+    }
+}
+
+/// Improves a solution by searching the box configurations close to it.
+///
+/// The window starts as the configurations the solution passes through, and an
+/// optimal path is searched for using only those. Whenever that fails to improve
+/// the solution, the window is widened by one push around every configuration on
+/// the path. Improvements are spliced in and the search is repeated around the new
+/// solution until the time limit or window size is reached.
+///
+/// The search goes push by push, making each with `Puzzle::move_box`, which walks the
+/// player to the box along a shortest path.
+pub struct Optimizer<'a> {
+    /// The level before any moves, with its movable positions up to date.
+    start: Puzzle,
+    config: &'a OptimizeConfig,
+    deadline: Instant,
+}
+
+impl<'a> Optimizer<'a> {
+    pub fn new(start: Puzzle, config: &'a OptimizeConfig) -> Self {
+        Self {
+            start,
+            config,
+            deadline: Instant::now() + config.time_limit,
+        }
+    }
+
+    /// Returns the best solution found. `solution` must be a valid solution.
+    pub fn optimize(&self, solution: &Solution) -> Solution {
+        let mut best = solution.clone();
+        let mut radius = 0;
+        let mut last_window_len = 0;
+
+        while !self.timed_out() {
+            let window = match self.window(&best, radius) {
+                Some(window) => window,
+                None => break,
+            };
+
+            let improved = match self.search(&window) {
+                Some(candidate) if self.cost(&candidate) < self.cost(&best) => {
+                    best = candidate;
+                    true
+                }
+                Some(_) => false,
+                None => break,
+            };
+
+            if !improved {
+                // The window covers everything reachable, so `best` is optimal.
+                if window.len() == last_window_len {
+                    break;
+                }
+                last_window_len = window.len();
+                radius += 1;
+            }
+        }
+
+        best
+    }
+
+    fn cost(&self, solution: &Solution) -> (usize, usize) {
+        self.config
+            .metric
+            .cost(solution.move_count(), solution.push_count())
+    }
+
+    fn timed_out(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+}
+
+impl<'a> Optimizer<'a> {
+    /// Returns the puzzle after each push of `solution`, including the starting one,
+    /// without their moves.
+    fn path_states(&self, solution: &Solution) -> Vec<Puzzle> {
+        let mut puzzle = self.start.clone();
+        let mut states = vec![puzzle.clone()];
+
+        for m in solution.moves() {
+            if puzzle.step(m.dir) == Ok(StepOutcome::Pushed) {
+                let mut state = puzzle.clone();
+                state.moves.clear();
+                states.push(state);
+            }
+        }
+        states
+    }
+
+    /// Returns every box configuration within `radius` pushes of the path of `solution`.
+    /// Returns `None` if the window grows too large or time runs out.
+    fn window(&self, solution: &Solution, radius: usize) -> Option<FxHashSet<Boxes>> {
+        let mut frontier = self.path_states(solution);
+        let mut seen = frontier.iter().map(state).collect::<FxHashSet<_>>();
+        let mut window = seen
+            .iter()
+            .map(|(boxes, _)| boxes.clone())
+            .collect::<FxHashSet<_>>();
+
+        for _ in 0..radius {
+            let mut next = vec![];
+            for puzzle in frontier {
+                if self.timed_out() {
+                    return None;
+                }
+
+                let boxes = state(&puzzle).0;
+                for (box_pos, dir) in pushes(&puzzle) {
+                    let new_state = pushed(&puzzle, &boxes, box_pos, dir);
+                    if !seen.contains(&new_state) {
+                        window.insert(new_state.0.clone());
+                        seen.insert(new_state);
+
+                        let mut new_puzzle = push(&puzzle, box_pos, dir);
+                        new_puzzle.moves.clear();
+                        next.push(new_puzzle);
+                    }
+                }
+
+                if window.len() > self.config.max_window {
+                    return None;
+                }
+            }
+            frontier = next;
+        }
+
+        Some(window)
+    }
+
+    /// Finds the cheapest solution that only passes through box configurations in
+    /// `window`, searching push by push. Returns `None` if time runs out.
+    fn search(&self, window: &FxHashSet<Boxes>) -> Option<Solution> {
+        let mut nodes = vec![Node {
+            puzzle: Some(self.start.clone()),
+            parent: None,
+            move_count: 0,
+        }];
+        let start_cost = self.config.metric.cost(0, 0);
+        let mut best = FxHashMap::from_iter([(state(&self.start), start_cost)]);
+        let mut expanded = FxHashSet::default();
+        let mut heap = BinaryHeap::from([Reverse((start_cost, 0))]);
+
+        while let Some(Reverse((_, idx))) = heap.pop() {
+            if expanded.len().is_multiple_of(256) && self.timed_out() {
+                return None;
+            }
+            let puzzle = nodes[idx].puzzle.take().expect("each node is queued once");
+            if !expanded.insert(state(&puzzle)) {
+                // Reached again since, more cheaply.
+                continue;
+            }
+            if puzzle.is_solved() {
+                return Some(self.to_solution(&rebuild(&nodes, idx)));
+            }
+
+            // Checked before pushing, as most pushes leave the window or cost too much.
+            let boxes = state(&puzzle).0;
+            let distances = puzzle.walk_distances();
+            for (box_pos, dir) in pushes(&puzzle) {
+                let new_state = pushed(&puzzle, &boxes, box_pos, dir);
+                if !window.contains(&new_state.0) || expanded.contains(&new_state) {
+                    continue;
+                }
+                let push_pos = puzzle.board().step(box_pos, dir.opposite(), 1).unwrap();
+                let move_count = nodes[idx].move_count + distances[&push_pos] + 1;
+                let new_cost = self.config.metric.cost(move_count, puzzle.pushes + 1);
+                if best.get(&new_state).is_some_and(|&c| c <= new_cost) {
+                    continue;
+                }
+
+                let mut new_puzzle = push(&puzzle, box_pos, dir);
+                best.insert(new_state, new_cost);
+                heap.push(Reverse((new_cost, nodes.len())));
+                let moves = mem::take(&mut new_puzzle.moves);
+                nodes.push(Node {
+                    puzzle: Some(new_puzzle),
+                    parent: Some((idx, moves)),
+                    move_count,
+                });
+            }
+        }
+
+        None
+    }
+
+    /// Replays `dirs` from the start to work out which of them are pushes.
+    fn to_solution(&self, dirs: &[Dir]) -> Solution {
+        let mut puzzle = self.start.clone();
+        let moves = dirs
+            .iter()
+            .map(|&dir| {
+                let outcome = puzzle
+                    .step(dir)
+                    .expect("the optimizer made an illegal move");
+                Move {
+                    dir,
+                    push: outcome == StepOutcome::Pushed,
+                }
+            })
+            .collect();
+        Solution::new(moves)
+    }
+}
+
+/// A position reached by the search.
+struct Node {
+    /// Taken out once the node is expanded. Only has the moves made since `parent`.
+    puzzle: Option<Puzzle>,
+    /// The node this one was pushed from, and the moves that led here from it.
+    parent: Option<(usize, Vec<Dir>)>,
+    /// The number of moves made since the start.
+    move_count: usize,
+}
+
+/// Returns the moves from the start that lead to `nodes[idx]`.
+fn rebuild(nodes: &[Node], mut idx: usize) -> Vec<Dir> {
+    let mut segments = vec![];
+    while let Some((parent, moves)) = &nodes[idx].parent {
+        segments.push(moves);
+        idx = *parent;
+    }
+    segments.into_iter().rev().flatten().copied().collect()
+}
+
+/// Returns the boxes that can be pushed one square from `puzzle`, and in which directions.
+fn pushes(puzzle: &Puzzle) -> Vec<(usize, Dir)> {
+    puzzle
+        .find_all_pushes(true)
+        .flat_map(|(box_pos, dirs)| {
+            dirs.iter()
+                .filter(|(_, &steps)| steps > 0)
+                .map(|(dir, _)| (box_pos, dir))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Returns `puzzle` after the player walks to the box at `box_pos` and pushes it one
+/// square in `dir`.
+fn push(puzzle: &Puzzle, box_pos: usize, dir: Dir) -> Puzzle {
+    let mut new_puzzle = puzzle.clone();
+    new_puzzle.move_box(box_pos, dir, 1);
+    new_puzzle
+}
+
+fn state(puzzle: &Puzzle) -> State {
+    let mut boxes = puzzle.boxes.iter().copied().collect::<Vec<_>>();
+    boxes.sort_unstable();
+    (boxes, puzzle.player_pos)
+}
+
+/// Returns the state that `push` would lead to, without making the moves. `boxes` are
+/// the sorted boxes of `puzzle`.
+fn pushed(puzzle: &Puzzle, boxes: &[usize], box_pos: usize, dir: Dir) -> State {
+    let new_pos = puzzle.board().step(box_pos, dir, 1).unwrap();
+    let mut boxes = boxes.to_vec();
+    let idx = boxes.binary_search(&box_pos).unwrap();
+    boxes.remove(idx);
+    let idx = boxes.binary_search(&new_pos).unwrap_err();
+    boxes.insert(idx, new_pos);
+    (boxes, box_pos)
+}
@@ -0,0 +1,193 @@
+//! Randomised checks of the rules and the deadlock detector on small levels.
+
+use rustc_hash::FxHashSet;
+use std::collections::HashSet;
+
+use super::board::Board;
+use super::deadlock::Detector;
+use super::directions::Dir;
+use super::puzzle::Puzzle;
+use super::StepOutcome;
+use crate::generate::{layout, LayoutStyle, Rng};
+use crate::question::{Position, Question};
+
+/// Makes a small level with up to three boxes, or `None` if the layout didn't work out.
+fn random_puzzle(rng: &mut Rng) -> Option<Puzzle> {
+    let style = *rng.choose(&[LayoutStyle::Templates, LayoutStyle::RandomWalk])?;
+    let grid = layout(5 + rng.below(3), 5 + rng.below(2), style, rng)?;
+    let mut floor = grid
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, sq)| **sq == crate::question::Square::Space)
+                .map(move |(x, _)| crate::question::Position::new(x, y))
+        })
+        .collect::<Vec<_>>();
+    let boxes = 1 + rng.below(3);
+    if floor.len() < boxes * 2 + 1 {
+        return None;
+    }
+    rng.shuffle(&mut floor);
+    let question = Question::from_parts(
+        grid,
+        floor[..boxes].iter().copied().collect(),
+        floor[boxes..boxes * 2].iter().copied().collect(),
+        floor[boxes * 2],
+    )
+    .ok()?;
+    Some(super::create_puzzle(&question))
+}
+
+/// Returns the squares the player can walk to without moving a box.
+fn flood_fill(board: &Board, player: usize, boxes: &FxHashSet<usize>) -> FxHashSet<usize> {
+    let mut area = FxHashSet::from_iter([player]);
+    let mut bag = vec![player];
+    while let Some(pos) = bag.pop() {
+        for next in board.borders(pos) {
+            if board.square_at(next).is_space() && !boxes.contains(&next) && area.insert(next) {
+                bag.push(next);
+            }
+        }
+    }
+    area
+}
+
+/// Returns every push the player can walk to and make, as the box moved and where to.
+fn pushes(board: &Board, player: usize, boxes: &FxHashSet<usize>) -> Vec<(usize, usize)> {
+    let area = flood_fill(board, player, boxes);
+    let mut pushes = vec![];
+    for &pos in boxes {
+        for dir in Dir::iter() {
+            let from = board.step(pos, dir.opposite(), 1);
+            let to = board.step(pos, dir, 1);
+            if let (Some(from), Some(to)) = (from, to) {
+                if area.contains(&from) && board.square_at(to).is_space() && !boxes.contains(&to) {
+                    pushes.push((pos, to));
+                }
+            }
+        }
+    }
+    pushes
+}
+
+/// Searches every position reachable from this one for a solution, without any pruning.
+fn is_solvable(board: &Board, player: usize, boxes: FxHashSet<usize>) -> bool {
+    let key = |player: usize, boxes: &FxHashSet<usize>| {
+        let mut sorted = boxes.iter().copied().collect::<Vec<_>>();
+        sorted.sort_unstable();
+        let area = flood_fill(board, player, boxes);
+        (*area.iter().min().unwrap(), sorted)
+    };
+
+    let mut seen = HashSet::from([key(player, &boxes)]);
+    let mut bag = vec![(player, boxes)];
+    while let Some((player, boxes)) = bag.pop() {
+        if boxes.iter().all(|pos| board.targets.contains(pos)) {
+            return true;
+        }
+        for (from, to) in pushes(board, player, &boxes) {
+            let mut next = boxes.clone();
+            next.remove(&from);
+            next.insert(to);
+            if seen.insert(key(from, &next)) {
+                bag.push((from, next));
+            }
+        }
+    }
+    false
+}
+
+#[test]
+fn random_moves_keep_the_rules() {
+    let mut rng = Rng::new(49);
+    let mut checked = 0;
+    while checked < 200 {
+        let Some(mut puzzle) = random_puzzle(&mut rng) else {
+            continue;
+        };
+        checked += 1;
+        let box_count = puzzle.boxes.len();
+        let dirs = Dir::iter().collect::<Vec<_>>();
+        for _ in 0..100 {
+            let dir = *rng.choose(&dirs).unwrap();
+            let before = (puzzle.player_pos, puzzle.boxes.clone());
+            let outcome = puzzle.step(dir);
+            if !matches!(outcome, Ok(StepOutcome::Walked | StepOutcome::Pushed)) {
+                assert_eq!((puzzle.player_pos, puzzle.boxes.clone()), before);
+            }
+
+            let board = puzzle.board();
+            assert_eq!(puzzle.boxes.len(), box_count);
+            assert!(puzzle
+                .boxes
+                .iter()
+                .all(|&pos| board.square_at(pos).is_space()));
+            assert!(!puzzle.boxes.contains(&puzzle.player_pos));
+            assert_eq!(
+                puzzle.movable_positions,
+                flood_fill(board, puzzle.player_pos, &puzzle.boxes),
+                "after {:?} from\n{}",
+                dir,
+                puzzle.view_movable_positions()
+            );
+        }
+    }
+}
+
+#[test]
+fn deadlocks_are_never_solvable() {
+    let mut rng = Rng::new(4949);
+    let (mut checked, mut deadlocks) = (0, 0);
+    while checked < 300 {
+        let Some(mut puzzle) = random_puzzle(&mut rng) else {
+            continue;
+        };
+        checked += 1;
+        let detector = Detector::new(puzzle.board());
+        let board = puzzle.board().clone();
+
+        // Looks at every push from a few positions along a random walk.
+        for _ in 0..5 {
+            for (from, to) in pushes(&board, puzzle.player_pos, &puzzle.boxes) {
+                let mut boxes = puzzle.boxes.clone();
+                boxes.remove(&from);
+                boxes.insert(to);
+                if let Some(deadlock) = detector.is_deadlocked(from, &boxes, to) {
+                    deadlocks += 1;
+                    let pushed = Position::from_usize(to, board.width);
+                    assert!(deadlock.boxes().contains(&pushed), "{:?}", deadlock);
+                    assert!(
+                        !is_solvable(&board, from, boxes.clone()),
+                        "pushing the box at {} to {} was called a deadlock, {:?}, in\n{}",
+                        from,
+                        to,
+                        deadlock,
+                        puzzle.view_movable_positions()
+                    );
+                }
+                if let Some(deadlock) = detector.explain(from, &boxes) {
+                    assert!(
+                        !is_solvable(&board, from, boxes.clone()),
+                        "{:?} after pushing the box at {} to {} in\n{}",
+                        deadlock,
+                        from,
+                        to,
+                        puzzle.view_movable_positions()
+                    );
+                }
+            }
+
+            let moves = pushes(&board, puzzle.player_pos, &puzzle.boxes);
+            let Some(&(from, to)) = rng.choose(&moves) else {
+                break;
+            };
+            puzzle.boxes.remove(&from);
+            puzzle.boxes.insert(to);
+            puzzle.player_pos = from;
+            puzzle.update_movable_positions();
+        }
+    }
+    assert!(deadlocks > 100, "only {} deadlocks were checked", deadlocks);
+}
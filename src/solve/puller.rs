@@ -1,6 +1,8 @@
 use rustc_hash::FxHashSet;
+use std::collections::VecDeque;
 
 use super::board::Board;
+use super::directions::Dir;
 
 pub struct Puller {
     board: Board,
@@ -48,4 +50,60 @@ impl Puller {
 
         visited
     }
+
+    /// Returns the fewest pushes needed to get a box from each square to `target`,
+    /// ignoring all other boxes, or `None` for squares where it can't be done.
+    pub fn find_push_distances_from(&self, target: usize) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.board.grid.len()];
+        distances[target] = Some(0);
+        let mut bag = VecDeque::from([target]);
+
+        while let Some(current) = bag.pop_front() {
+            let dist = distances[current].unwrap();
+            for (dir, box_pos) in self.board.borders_with_dirs(current) {
+                if self.board.square_at(box_pos).is_wall() || distances[box_pos].is_some() {
+                    continue;
+                }
+
+                let player_pos = self.board.step(box_pos, dir, 1);
+                if player_pos.is_some_and(|pos| self.board.square_at(pos).is_space()) {
+                    distances[box_pos] = Some(dist + 1);
+                    bag.push_back(box_pos);
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// Returns every pull the player can make from where they are, as the square the
+    /// player pulls from and the direction they step in. The box on the square behind
+    /// them follows them onto that square.
+    pub fn find_pulls(&self, player_pos: usize, boxes: &FxHashSet<usize>) -> Vec<(usize, Dir)> {
+        let free = |pos: usize| self.board.square_at(pos).is_space() && !boxes.contains(&pos);
+
+        let mut reachable = FxHashSet::from_iter([player_pos]);
+        let mut bag = vec![player_pos];
+        while let Some(current) = bag.pop() {
+            for pos in self.board.borders(current) {
+                if free(pos) && reachable.insert(pos) {
+                    bag.push(pos);
+                }
+            }
+        }
+        let mut reachable = reachable.into_iter().collect::<Vec<_>>();
+        reachable.sort_unstable();
+
+        let mut pulls = vec![];
+        for pos in reachable {
+            for dir in Dir::iter() {
+                let behind = self.board.step(pos, dir.opposite(), 1);
+                let ahead = self.board.step(pos, dir, 1);
+                if behind.is_some_and(|pos| boxes.contains(&pos)) && ahead.is_some_and(free) {
+                    pulls.push((pos, dir));
+                }
+            }
+        }
+        pulls
+    }
 }
@@ -4,7 +4,10 @@ use std::rc::Rc;
 use std::{collections::VecDeque, fmt};
 
 use super::board::Board;
+use super::deadlock::Deadlock;
 use super::directions::{Dir, DirHolder};
+use super::game::{IllegalMove, StepOutcome};
+use crate::question::{Position, Question, Square};
 
 /// Joins a 2d vector of strings into a single output string.
 ///
@@ -27,6 +30,8 @@ pub struct Puzzle {
     pub boxes: FxHashSet<usize>,
     pub player_pos: usize,
     pub moves: Vec<Dir>,
+    /// The number of pushes made so far.
+    pub pushes: usize,
 
     /// `movable_positions` should always be kept updated.
     pub movable_positions: FxHashSet<usize>,
@@ -40,10 +45,15 @@ impl Puzzle {
             boxes,
 
             moves: Vec::default(),
+            pushes: 0,
             movable_positions: FxHashSet::default(),
         }
     }
 
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
     pub fn moves(&self) -> &Vec<Dir> {
         &self.moves
     }
@@ -56,6 +66,33 @@ impl Puzzle {
     }
 }
 
+impl Puzzle {
+    /// Returns the position as a level that starts where the player and boxes are now.
+    pub fn to_question(&self) -> Question {
+        let board = &self.board;
+        let position = |pos: usize| Position::from_usize(pos, board.width);
+        let grid = board
+            .grid
+            .chunks_exact(board.width)
+            .map(|row| {
+                row.iter()
+                    .map(|sq| match sq.is_space() {
+                        true => Square::Space,
+                        false => Square::Wall,
+                    })
+                    .collect()
+            })
+            .collect();
+        Question::from_parts(
+            grid,
+            self.boxes.iter().map(|&pos| position(pos)).collect(),
+            board.targets.iter().map(|&pos| position(pos)).collect(),
+            position(self.player_pos),
+        )
+        .expect("the player and boxes are on floor")
+    }
+}
+
 impl fmt::Display for Puzzle {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", vec2d_to_string(self.get_2d_grid_vec()))
@@ -68,6 +105,7 @@ impl Puzzle {
         self.board.to_2d_grid_str(self.player_pos, &self.boxes)
     }
 
+    #[allow(dead_code)]
     pub fn view_valid_positions(&self) -> String {
         let mut grid = self.get_2d_grid_vec();
         for (pos, sq) in self.board.grid.iter().enumerate() {
@@ -78,7 +116,20 @@ impl Puzzle {
         vec2d_to_string(grid)
     }
 
+    /// Returns a string view of the boxes (`X`) and squares (`x`) involved in `deadlock`.
+    pub fn view_deadlock(&self, deadlock: &Deadlock) -> String {
+        let mut grid = self.get_2d_grid_vec();
+        for pos in deadlock.squares() {
+            grid[pos.y()][pos.x()] = "x";
+        }
+        for pos in deadlock.boxes() {
+            grid[pos.y()][pos.x()] = "X";
+        }
+        vec2d_to_string(grid)
+    }
+
     /// Returns a string view of the movable positions in the grid.
+    #[allow(dead_code)]
     pub fn view_movable_positions(&self) -> String {
         let mut grid = self.get_2d_grid_vec();
         for &pos in self.movable_positions.iter() {
@@ -95,9 +146,7 @@ impl Puzzle {
         let mut bag = vec![self.player_pos];
         let mut visited = FxHashSet::from_iter(bag.clone());
 
-        while !bag.is_empty() {
-            let current = bag.pop().unwrap();
-
+        while let Some(current) = bag.pop() {
             for new_pos in self.board.borders(current) {
                 if self.is_pos_walkable(new_pos) && !visited.contains(&new_pos) {
                     bag.push(new_pos);
@@ -190,6 +239,7 @@ impl Puzzle {
         for _ in 0..steps {
             self.moves.push(dir);
         }
+        self.pushes += steps;
 
         new_box_pos
     }
@@ -202,6 +252,20 @@ impl Puzzle {
             target
         );
 
+        let moves = self.find_path(target).unwrap_or_else(|| {
+            panic!(
+                "pos: {} was in the movable positions, but it wasn't acutally able to be moved to",
+                target
+            )
+        });
+
+        self.update_player_pos(target, false);
+        self.add_moves(moves);
+    }
+
+    /// Returns the shortest walk from the player's position to `target` without
+    /// pushing any boxes, or `None` if there isn't one.
+    pub fn find_path(&self, target: usize) -> Option<VecDeque<Dir>> {
         let mut bag = VecDeque::new();
         bag.push_back(self.player_pos);
 
@@ -215,18 +279,15 @@ impl Puzzle {
 
             for (dir, new_pos) in self.board.borders_with_dirs(current) {
                 if self.is_pos_walkable(new_pos) && !visited.contains_key(&new_pos) {
-                    assert!(!self.board.square_at(new_pos).is_wall());
                     bag.push_back(new_pos);
                     visited.insert(new_pos, Some(dir));
                 }
             }
         }
 
-        assert!(
-            visited.contains_key(&target),
-            "pos: {} was in the movable positions, but it wasn't acutally able to be moved to",
-            target
-        );
+        if !visited.contains_key(&target) {
+            return None;
+        }
 
         let mut moves = VecDeque::new();
         let mut pos = target;
@@ -237,9 +298,76 @@ impl Puzzle {
                 .step(pos, dir.opposite(), 1)
                 .expect("Rebuilding path encountered out of bounds position.");
         }
+        Some(moves)
+    }
 
-        self.update_player_pos(target, false);
-        self.add_moves(moves);
+    /// Returns how many steps the player needs to walk to each square it can reach
+    /// without pushing any boxes.
+    pub fn walk_distances(&self) -> FxHashMap<usize, usize> {
+        let mut bag = VecDeque::from([self.player_pos]);
+        let mut distances = FxHashMap::from_iter([(self.player_pos, 0)]);
+
+        while let Some(current) = bag.pop_front() {
+            let distance = distances[&current] + 1;
+            for new_pos in self.board.borders(current) {
+                if self.is_pos_walkable(new_pos) && !distances.contains_key(&new_pos) {
+                    distances.insert(new_pos, distance);
+                    bag.push_back(new_pos);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Moves the player a single square in `dir`, pushing the box in the way if there is one.
+    ///
+    /// If the move is blocked or illegal, the puzzle is left unchanged.
+    pub fn step(&mut self, dir: Dir) -> Result<StepOutcome, IllegalMove> {
+        let new_pos = self
+            .board
+            .step(self.player_pos, dir, 1)
+            .ok_or(IllegalMove::OffBoard)?;
+        if self.is_pos_walkable(new_pos) {
+            self.player_pos = new_pos;
+            self.moves.push(dir);
+            return Ok(StepOutcome::Walked);
+        }
+
+        if !self.boxes.contains(&new_pos) {
+            return Ok(StepOutcome::Blocked);
+        }
+        let new_box_pos = self
+            .board
+            .step(new_pos, dir, 1)
+            .ok_or(IllegalMove::BoxOffBoard)?;
+        if !self.is_pos_walkable(new_box_pos) {
+            return Ok(StepOutcome::Blocked);
+        }
+
+        self.update_box_pos(new_pos, new_box_pos);
+        self.update_player_pos(new_pos, true);
+        self.moves.push(dir);
+        self.pushes += 1;
+        Ok(StepOutcome::Pushed)
+    }
+
+    /// Takes back the last `step`, which moved in `dir` and pushed a box if `push` is true.
+    pub fn unstep(&mut self, dir: Dir, push: bool) {
+        let back = self
+            .board
+            .step(self.player_pos, dir.opposite(), 1)
+            .expect("the step being taken back came from out of bounds");
+        if push {
+            let box_pos = self
+                .board
+                .step(self.player_pos, dir, 1)
+                .expect("the box being pulled back is out of bounds");
+            self.update_box_pos(box_pos, self.player_pos);
+            self.pushes -= 1;
+        }
+
+        self.moves.pop();
+        self.update_player_pos(back, push);
     }
 
     fn update_box_pos(&mut self, old_pos: usize, new_pos: usize) {
@@ -276,6 +404,7 @@ impl Puzzle {
 
     pub fn get_encoding(&self) -> Vec<usize> {
         let mut v = self.boxes.iter().copied().collect::<Vec<_>>();
+        v.sort_unstable();
         v.push(self.player_pos);
         v
     }
@@ -0,0 +1,174 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::{SolveResult, SolveStatus};
+use crate::json;
+use crate::question::{ParseError, Question};
+
+/// How to write out records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// One JSON object per line.
+    JsonLines,
+    /// Comma separated values, with a header line.
+    Csv,
+}
+
+impl FromStr for RecordFormat {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "jsonl" | "json" => Ok(Self::JsonLines),
+            "csv" => Ok(Self::Csv),
+            _ => Err(ParseError::Text(format!(
+                "unknown record format {:?}, expected jsonl or csv",
+                s
+            ))),
+        }
+    }
+}
+
+impl RecordFormat {
+    /// Returns the line to write before any records, if the format has one.
+    pub fn header(&self) -> Option<String> {
+        match self {
+            Self::JsonLines => None,
+            Self::Csv => Some(Record::FIELDS.join(",")),
+        }
+    }
+
+    /// Returns `record` written as a single line, without the newline.
+    pub fn write(&self, record: &Record) -> String {
+        match self {
+            Self::JsonLines => record.to_json(),
+            Self::Csv => record.to_csv(),
+        }
+    }
+}
+
+/// The outcome of solving one level, in a form that can be written as JSON or CSV.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// The position of the level in its collection, starting from 1.
+    pub level: usize,
+    pub title: Option<String>,
+    /// The file and position the level was read from, such as `original.txt:3`.
+    pub source: Option<String>,
+    pub status: SolveStatus,
+    pub moves: Option<usize>,
+    pub pushes: Option<usize>,
+    pub lurd: Option<String>,
+    pub expanded: usize,
+    pub visited: usize,
+    pub peak_memory: usize,
+    /// How long the level took, in seconds.
+    pub time: f64,
+}
+
+impl Record {
+    /// The names of the fields, in the order they are written.
+    pub const FIELDS: [&'static str; 11] = [
+        "level",
+        "title",
+        "source",
+        "status",
+        "moves",
+        "pushes",
+        "lurd",
+        "expanded",
+        "visited",
+        "peak_memory",
+        "time",
+    ];
+
+    /// Creates the record for `question`, the level at index `idx` of its collection.
+    pub fn new(idx: usize, question: &Question, result: &SolveResult) -> Self {
+        let solution = result.solution.as_ref();
+        Self {
+            level: idx + 1,
+            title: question.title().map(str::to_string),
+            source: question.source().map(|source| source.to_string()),
+            status: result.status,
+            moves: solution.map(|solution| solution.move_count()),
+            pushes: solution.map(|solution| solution.push_count()),
+            lurd: solution.map(|solution| solution.to_string()),
+            expanded: result.expanded,
+            visited: result.visited,
+            peak_memory: result.peak_memory,
+            time: result.elapsed.as_secs_f64(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let number = |n: Option<usize>| n.map_or("null".to_string(), |n| n.to_string());
+        json::Object::new()
+            .number("level", self.level)
+            .optional("title", self.title.as_deref())
+            .optional("source", self.source.as_deref())
+            .string("status", &self.status.to_string())
+            .raw("moves", number(self.moves))
+            .raw("pushes", number(self.pushes))
+            .optional("lurd", self.lurd.as_deref())
+            .number("expanded", self.expanded)
+            .number("visited", self.visited)
+            .number("peak_memory", self.peak_memory)
+            .number("time", format!("{:.6}", self.time))
+            .to_string()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let number = |n: Option<usize>| n.map(|n| n.to_string()).unwrap_or_default();
+        [
+            self.level.to_string(),
+            csv_field(self.title.as_deref().unwrap_or_default()),
+            csv_field(self.source.as_deref().unwrap_or_default()),
+            self.status.to_string(),
+            number(self.moves),
+            number(self.pushes),
+            self.lurd.clone().unwrap_or_default(),
+            self.expanded.to_string(),
+            self.visited.to_string(),
+            self.peak_memory.to_string(),
+            format!("{:.6}", self.time),
+        ]
+        .join(",")
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_json())
+    }
+}
+
+/// Quotes `s` if it contains anything that would break up a CSV line.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solve::{solve_puzzle, SolverConfig};
+
+    #[test]
+    fn records() {
+        let mut question = Question::from_str("#####\n#@$.#\n#####").unwrap();
+        question.set_metadata("Title", "Say \"hi\", then push");
+        let result = solve_puzzle(&question, &SolverConfig::default());
+        let record = Record::new(0, &question, &result);
+
+        let json = record.to_json();
+        assert!(json.starts_with(r#"{"level":1,"title":"Say \"hi\", then push","source":null,"#));
+        assert!(json.contains(r#""status":"solved","moves":1,"pushes":1,"lurd":"R","#));
+
+        let csv = RecordFormat::Csv.write(&record);
+        assert!(csv.starts_with(r#"1,"Say ""hi"", then push",,solved,1,1,R,"#));
+        assert_eq!(csv.split(',').count(), Record::FIELDS.len() + 1);
+    }
+}
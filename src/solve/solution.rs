@@ -0,0 +1,161 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use super::directions::Dir;
+use super::game::{Game, StepOutcome};
+use crate::question::{ParseError, Question, SavedSolution, Symmetry};
+
+/// A single step of the player, and whether it pushed a box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub dir: Dir,
+    pub push: bool,
+}
+
+/// A sequence of player steps, written out in LURD notation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Solution {
+    moves: Vec<Move>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The step at this index walked into a wall or an immovable box.
+    Blocked(usize),
+    /// The step at this index was marked as a push but walked, or the other way round.
+    PushMismatch(usize),
+    /// Every step was legal, but the boxes did not all end up on targets.
+    Unsolved,
+}
+
+impl Error for ReplayError {}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Blocked(idx) => write!(f, "move {} is blocked", idx + 1),
+            Self::PushMismatch(idx) => {
+                write!(
+                    f,
+                    "move {} does not match the push in the solution",
+                    idx + 1
+                )
+            }
+            Self::Unsolved => write!(f, "the solution does not solve the level"),
+        }
+    }
+}
+
+impl Solution {
+    pub fn new(moves: Vec<Move>) -> Self {
+        Self { moves }
+    }
+
+    /// Replays `dirs` on `question` to work out which steps are pushes.
+    ///
+    /// Does not check that the level ends up solved.
+    pub fn from_dirs(question: &Question, dirs: &[Dir]) -> Result<Self, ReplayError> {
+        let mut game = Game::new(question);
+        for (idx, &dir) in dirs.iter().enumerate() {
+            match game.step(dir) {
+                Ok(StepOutcome::Walked | StepOutcome::Pushed) => (),
+                _ => return Err(ReplayError::Blocked(idx)),
+            }
+        }
+        Ok(Self::new(game.history().to_vec()))
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    pub fn dirs(&self) -> Vec<Dir> {
+        self.moves.iter().map(|m| m.dir).collect()
+    }
+
+    pub fn move_count(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn push_count(&self) -> usize {
+        self.moves.iter().filter(|m| m.push).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// Returns the matching solution for the level transformed by `symmetry`.
+    pub fn transform(&self, symmetry: Symmetry) -> Self {
+        let moves = self
+            .moves
+            .iter()
+            .map(|m| {
+                let c = symmetry.transform_lurd_char(m.dir.to_lurd(m.push));
+                let (dir, push) = Dir::from_lurd(c).unwrap();
+                Move { dir, push }
+            })
+            .collect();
+        Self { moves }
+    }
+
+    /// Plays the solution from the start of `question`, checking that every step is
+    /// legal and that pushes are marked as pushes. Returns the game at the end.
+    pub fn replay(&self, question: &Question) -> Result<Game, ReplayError> {
+        let mut game = Game::new(question);
+        match game.play(self) {
+            Ok(()) => Ok(game),
+            Err(idx) => Err(match game.step(self.moves[idx].dir) {
+                Ok(StepOutcome::Walked | StepOutcome::Pushed) => ReplayError::PushMismatch(idx),
+                _ => ReplayError::Blocked(idx),
+            }),
+        }
+    }
+
+    /// Checks that every step is legal, that pushes are marked as pushes, and that
+    /// the level is solved at the end.
+    pub fn verify(&self, question: &Question) -> Result<(), ReplayError> {
+        if self.replay(question)?.is_solved() {
+            Ok(())
+        } else {
+            Err(ReplayError::Unsolved)
+        }
+    }
+}
+
+impl fmt::Display for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for m in self.moves.iter() {
+            write!(f, "{}", m.dir.to_lurd(m.push))?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&Solution> for SavedSolution {
+    fn from(solution: &Solution) -> Self {
+        Self {
+            lurd: solution.to_string(),
+            snapshot: false,
+        }
+    }
+}
+
+impl FromStr for Solution {
+    type Err = ParseError;
+
+    /// Parses a LURD string. Whitespace is ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let moves = s
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| {
+                Dir::from_lurd(c)
+                    .map(|(dir, push)| Move { dir, push })
+                    .ok_or_else(|| ParseError::Text(format!("invalid LURD character {:?}", c)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { moves })
+    }
+}
@@ -1,59 +1,225 @@
-// #![allow(dead_code, unused_imports)]
-
-use rustc_hash::FxHashSet;
-use std::collections::VecDeque;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::mem;
 use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 use super::board::Board;
+use super::bounds::LowerBound;
 use super::deadlock::Detector;
-use super::puller::Puller;
+use super::directions::Dir;
 use super::puzzle::Puzzle;
-use super::squares::Flags;
 
-use crate::question;
+use crate::question::{self, ParseError};
+
+/// The order in which the solver looks at positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    /// Breadth first over pushes. Finds solutions with the fewest box moves (lines).
+    #[default]
+    Bfs,
+    /// Fewest pushes made plus the pushes still needed at least. Finds push optimal solutions.
+    AStar,
+    /// Whatever looks closest to being solved first. Fast, but solutions can be long.
+    Greedy,
+}
+
+impl Algorithm {
+    pub fn names() -> &'static [&'static str] {
+        &["bfs", "astar", "greedy"]
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bfs" => Ok(Self::Bfs),
+            "astar" | "a*" => Ok(Self::AStar),
+            "greedy" => Ok(Self::Greedy),
+            _ => Err(ParseError::Text(format!(
+                "unknown algorithm {:?}, expected one of {}",
+                s,
+                Self::names().join(", ")
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Bfs => "bfs",
+            Self::AStar => "astar",
+            Self::Greedy => "greedy",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SolverConfig {
+    pub algorithm: Algorithm,
+    /// Give up after expanding this many positions.
+    pub max_nodes: Option<usize>,
+    pub time_limit: Option<Duration>,
+    /// Print the position being looked at to stderr every this many positions.
+    pub progress: Option<usize>,
+}
+
+/// How a search ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveStatus {
+    Solved,
+    /// Every reachable position was searched without finding a solution.
+    Unsolvable,
+    NodeLimit,
+    TimeLimit,
+    /// The solver's moves didn't play out on the level, which is a bug in the solver.
+    Failed,
+}
+
+impl fmt::Display for SolveStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Solved => "solved",
+            Self::Unsolvable => "unsolvable",
+            Self::NodeLimit => "node-limit",
+            Self::TimeLimit => "time-limit",
+            Self::Failed => "failed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A position waiting in the queue.
+struct Queued {
+    priority: usize,
+    /// When the position was queued. Earlier positions come first on ties.
+    order: usize,
+    puzzle: Puzzle,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    /// Reversed, so that the `BinaryHeap` pops the lowest priority first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.priority, other.order).cmp(&(self.priority, self.order))
+    }
+}
+
+/// Roughly how many bytes a queued position takes up, including its heap allocations.
+fn memory_size(puzzle: &Puzzle) -> usize {
+    // Hash sets store a control byte alongside each slot.
+    let set_size = |capacity: usize| capacity * (mem::size_of::<usize>() + 1);
+    mem::size_of::<Queued>()
+        + set_size(puzzle.boxes.capacity())
+        + set_size(puzzle.movable_positions.capacity())
+        + puzzle.moves.capacity() * mem::size_of::<Dir>()
+}
 
 pub struct Solver {
-    // board: Rc<Board>,
-    puzzles: VecDeque<Puzzle>,
+    puzzles: BinaryHeap<Queued>,
     detector: Detector,
-    visited: FxHashSet<Vec<usize>>,
+    bound: LowerBound,
+    /// The fewest pushes each position seen has been reached in.
+    visited: FxHashMap<Vec<usize>, usize>,
+    config: SolverConfig,
+    queued: usize,
+    expanded: usize,
+    /// Roughly how many bytes the queue and visited set take up, now and at most.
+    memory: usize,
+    peak_memory: usize,
 }
 
 impl Solver {
-    pub fn solve(&mut self) {
-        let mut solved_puzzle = None;
-
-        let mut count = -1;
-
-        // let ds = 1;
-        let ds = 10000;
-
-        while let Some(puzzle) = self.puzzles.pop_front() {
-            count += 1;
-            if count % ds == 0 {
-                println!("\n{} count: {} {}", "-".repeat(30), count, "-".repeat(30));
-                println!("Looking at puzzle:\n{}\n", puzzle);
-                println!("moves: {:?}", puzzle.moves());
-                // println!("movable_positions:\n{}", puzzle.view_movable_positions());
-                // println!("valid positions:\n{}", puzzle.view_valid_positions());
+    /// Searches for a solution, returning how the search ended and the moves that
+    /// solve the puzzle if one was found.
+    pub fn solve(&mut self) -> (SolveStatus, Option<Vec<Dir>>) {
+        let start = Instant::now();
+
+        while let Some(Queued { puzzle, .. }) = self.puzzles.pop() {
+            self.memory -= memory_size(&puzzle);
+            if self.config.algorithm == Algorithm::AStar
+                && self
+                    .visited
+                    .get(&puzzle.get_encoding())
+                    .is_some_and(|&pushes| pushes < puzzle.pushes)
+            {
+                // Queued again since, in fewer pushes.
+                continue;
+            }
+            if let Some(interval) = self.config.progress {
+                if self.expanded.is_multiple_of(interval) {
+                    eprintln!(
+                        "\n{} count: {} {}",
+                        "-".repeat(30),
+                        self.expanded,
+                        "-".repeat(30)
+                    );
+                    eprintln!("Looking at puzzle:\n{}\n", puzzle);
+                    eprintln!("moves: {:?}", puzzle.moves());
+                }
             }
 
             if puzzle.is_solved() {
-                solved_puzzle = Some(puzzle);
-                break;
+                return (SolveStatus::Solved, Some(puzzle.moves().clone()));
             }
 
+            if self
+                .config
+                .max_nodes
+                .is_some_and(|max| self.expanded >= max)
+            {
+                return (SolveStatus::NodeLimit, None);
+            }
+            if self.expanded.is_multiple_of(256)
+                && self
+                    .config
+                    .time_limit
+                    .is_some_and(|limit| start.elapsed() >= limit)
+            {
+                return (SolveStatus::TimeLimit, None);
+            }
+
+            self.expanded += 1;
             self.expand(puzzle);
         }
 
-        println!("total iterations: {}", count);
-        println!("visited: {}", self.visited.len());
-        if let Some(puzzle) = solved_puzzle {
-            println!("Solved:\n{}", puzzle);
-            println!("Moves: {:?}", puzzle.moves());
-        } else {
-            println!("unsolved....");
-        }
+        (SolveStatus::Unsolvable, None)
+    }
+
+    /// The number of positions that were taken off the queue and expanded.
+    pub fn expanded(&self) -> usize {
+        self.expanded
+    }
+
+    /// The number of distinct positions that were seen.
+    pub fn visited(&self) -> usize {
+        self.visited.len()
+    }
+
+    /// An estimate of the most bytes the search needed at once to remember the
+    /// positions it had seen and had yet to look at.
+    pub fn peak_memory(&self) -> usize {
+        self.peak_memory
     }
 
     fn expand(&mut self, puzzle: Puzzle) {
@@ -65,84 +231,91 @@ impl Solver {
                     let last_moved = new_puzzle.move_box(box_pos, dir, steps);
                     new_puzzle.move_to_top_left();
 
+                    // A* goes back to a position if it finds a way there in fewer
+                    // pushes, which it needs to for its solutions to be push optimal.
                     let encoding = new_puzzle.get_encoding();
-                    if self.visited.contains(&encoding) {
+                    let seen = self.visited.get(&encoding);
+                    if seen.is_some_and(|&pushes| {
+                        self.config.algorithm != Algorithm::AStar || pushes <= new_puzzle.pushes
+                    }) {
                         continue;
                     }
 
-                    if self.detector.is_deadlocked(
-                        new_puzzle.player_pos,
-                        &new_puzzle.boxes,
-                        last_moved,
-                    ) {
+                    if self
+                        .detector
+                        .is_deadlocked(new_puzzle.player_pos, &new_puzzle.boxes, last_moved)
+                        .is_some()
+                    {
                         break;
                     }
-                    self.visited.insert(encoding);
-                    self.puzzles.push_back(new_puzzle)
+                    if seen.is_none() {
+                        self.memory += mem::size_of::<Vec<usize>>()
+                            + encoding.len() * mem::size_of::<usize>()
+                            + 2 * mem::size_of::<u64>();
+                    }
+                    self.visited.insert(encoding, new_puzzle.pushes);
+                    self.push(new_puzzle);
                 }
             }
         }
     }
+
+    /// Adds `puzzle` to the queue, unless a box on it can never reach a target.
+    fn push(&mut self, puzzle: Puzzle) {
+        let Some(estimate) = self.bound.estimate(puzzle.boxes.iter().copied()) else {
+            return;
+        };
+        let priority = match self.config.algorithm {
+            Algorithm::Bfs => 0,
+            Algorithm::AStar => puzzle.pushes + estimate,
+            Algorithm::Greedy => estimate,
+        };
+
+        self.memory += memory_size(&puzzle);
+        self.peak_memory = self.peak_memory.max(self.memory);
+        self.puzzles.push(Queued {
+            priority,
+            order: self.queued,
+            puzzle,
+        });
+        self.queued += 1;
+    }
 }
 
 impl Solver {
-    fn _create(
-        mut grid: Vec<Flags>,
-        width: usize,
-        height: usize,
-        boxes: FxHashSet<usize>,
-        targets: FxHashSet<usize>,
+    pub fn new(
+        board: Board,
         start_pos: usize,
+        boxes: FxHashSet<usize>,
+        config: &SolverConfig,
     ) -> Self {
-        let puller = Puller::new(Board::new(width, height, grid.clone(), targets.clone()));
-
-        for pos in puller.find_all_valid_positions() {
-            grid[pos] |= Flags::VALID;
-        }
-
-        let board = Board::new(width, height, grid, targets);
         let detector = Detector::new(&board);
+        let bound = LowerBound::new(&board);
 
-        let rc_board = Rc::new(board);
-
-        let mut puzzle = Puzzle::new(Rc::clone(&rc_board), start_pos, boxes);
+        let mut puzzle = Puzzle::new(Rc::new(board), start_pos, boxes);
         puzzle.update_movable_positions();
         puzzle.move_to_top_left();
 
-        Self {
-            // board: rc_board,
-            puzzles: vec![puzzle].into(),
+        let mut solver = Self {
+            puzzles: BinaryHeap::new(),
             detector,
-            visited: FxHashSet::default(),
-        }
+            bound,
+            visited: FxHashMap::default(),
+            config: config.clone(),
+            queued: 0,
+            expanded: 0,
+            memory: 0,
+            peak_memory: 0,
+        };
+        solver.visited.insert(puzzle.get_encoding(), puzzle.pushes);
+        solver.push(puzzle);
+        solver
     }
 }
 
-impl<'a, Q: std::borrow::Borrow<question::Question>> From<Q> for Solver {
+impl<Q: std::borrow::Borrow<question::Question>> From<Q> for Solver {
     fn from(question: Q) -> Self {
-        let question = question.borrow();
-        let (width, height) = (question.width(), question.height());
-        let mut grid = Vec::with_capacity(width * height);
-        for row in question.rows() {
-            for sq in row {
-                grid.push(match *sq {
-                    question::Square::Wall => Flags::WALL,
-                    question::Square::Space => Flags::SPACE,
-                })
-            }
-        }
-
-        let start = question.start().to_usize(width);
-
-        let mapper = |it: &std::collections::HashSet<question::Position>| -> FxHashSet<usize> {
-            it.iter()
-                .map(|p| p.to_usize(width))
-                .collect::<FxHashSet<_>>()
-        };
-
-        let boxes = mapper(question.boxes());
-        let targets = mapper(question.targets());
-
-        Self::_create(grid, width, height, boxes, targets, start)
+        let (board, start, boxes) = super::create_board(question.borrow());
+        Self::new(board, start, boxes, &SolverConfig::default())
     }
 }
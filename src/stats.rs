@@ -0,0 +1,238 @@
+//! Measuring levels without solving them, to get an idea of what a collection holds
+//! before spending time on it.
+
+use std::collections::HashSet;
+
+use crate::json;
+use crate::question::{Question, QuestionCollection};
+use crate::solve::board::Board;
+use crate::solve::{self, Dir};
+
+/// What a level is made of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelStats {
+    pub width: usize,
+    pub height: usize,
+    pub boxes: usize,
+    /// Every floor square inside the level.
+    pub floor: usize,
+    /// The floor squares the player can get to, if no boxes were in the way.
+    pub reachable: usize,
+    /// Reachable floor squares that a box can't be pushed onto without deadlocking.
+    pub dead: usize,
+    /// Corridors one square wide, counted as a whole rather than square by square.
+    pub tunnels: usize,
+    /// The parts of the reachable floor that are left when the tunnels are taken out.
+    pub rooms: usize,
+    /// True if all the targets are in one room, and there is only one way into it.
+    pub goal_room: bool,
+    /// The fewest pushes a solution could need, or `None` if some box can't reach
+    /// any target.
+    pub push_lower_bound: Option<usize>,
+}
+
+impl LevelStats {
+    pub fn new(question: &Question) -> Self {
+        let (board, start, _) = solve::create_board(question);
+        let floor = (0..board.grid.len())
+            .filter(|&pos| board.square_at(pos).is_space())
+            .collect::<HashSet<_>>();
+        let reachable = component(&board, start, |pos| floor.contains(&pos));
+        let dead = reachable
+            .iter()
+            .filter(|&&pos| !board.square_at(pos).is_valid())
+            .count();
+
+        let tunnel_squares = reachable
+            .iter()
+            .copied()
+            .filter(|&pos| is_tunnel(&board, pos))
+            .collect::<HashSet<_>>();
+        let tunnels = components(&board, &tunnel_squares).len();
+        let room_squares = reachable
+            .difference(&tunnel_squares)
+            .copied()
+            .collect::<HashSet<_>>();
+        let rooms = components(&board, &room_squares);
+
+        // The room holding every target, if there is one, and the squares just outside it.
+        let goal_room = rooms
+            .iter()
+            .find(|room| board.targets.iter().all(|target| room.contains(target)))
+            .is_some_and(|room| {
+                let doors = room
+                    .iter()
+                    .flat_map(|&pos| board.borders(pos))
+                    .filter(|pos| reachable.contains(pos) && !room.contains(pos))
+                    .collect::<HashSet<_>>();
+                doors.len() == 1 && room.len() < reachable.len()
+            });
+
+        Self {
+            width: question.width(),
+            height: question.height(),
+            boxes: question.boxes().len(),
+            floor: floor.len(),
+            reachable: reachable.len(),
+            dead,
+            tunnels,
+            rooms: rooms.len(),
+            goal_room,
+            push_lower_bound: solve::push_lower_bound(question),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let bound = self
+            .push_lower_bound
+            .map_or("null".to_string(), |n| n.to_string());
+        json::Object::new()
+            .number("width", self.width)
+            .number("height", self.height)
+            .number("boxes", self.boxes)
+            .number("floor", self.floor)
+            .number("reachable", self.reachable)
+            .number("dead", self.dead)
+            .number("tunnels", self.tunnels)
+            .number("rooms", self.rooms)
+            .raw("goal_room", self.goal_room.to_string())
+            .raw("push_lower_bound", bound)
+            .to_string()
+    }
+}
+
+/// Totals and extremes over the levels of a collection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionStats {
+    pub levels: usize,
+    pub boxes: usize,
+    pub max_boxes: usize,
+    pub max_width: usize,
+    pub max_height: usize,
+    pub mean_reachable: f64,
+    pub mean_dead: f64,
+    pub goal_rooms: usize,
+    /// The levels where some box can't reach any target.
+    pub unsolvable: usize,
+    /// The push lower bounds of the other levels added up.
+    pub push_lower_bound: usize,
+}
+
+impl CollectionStats {
+    pub fn new<'a>(levels: impl IntoIterator<Item = &'a LevelStats>) -> Self {
+        let mut stats = Self {
+            levels: 0,
+            boxes: 0,
+            max_boxes: 0,
+            max_width: 0,
+            max_height: 0,
+            mean_reachable: 0.0,
+            mean_dead: 0.0,
+            goal_rooms: 0,
+            unsolvable: 0,
+            push_lower_bound: 0,
+        };
+        let (mut reachable, mut dead) = (0, 0);
+        for level in levels {
+            stats.levels += 1;
+            stats.boxes += level.boxes;
+            stats.max_boxes = stats.max_boxes.max(level.boxes);
+            stats.max_width = stats.max_width.max(level.width);
+            stats.max_height = stats.max_height.max(level.height);
+            reachable += level.reachable;
+            dead += level.dead;
+            stats.goal_rooms += level.goal_room as usize;
+            match level.push_lower_bound {
+                Some(bound) => stats.push_lower_bound += bound,
+                None => stats.unsolvable += 1,
+            }
+        }
+        if stats.levels > 0 {
+            stats.mean_reachable = reachable as f64 / stats.levels as f64;
+            stats.mean_dead = dead as f64 / stats.levels as f64;
+        }
+        stats
+    }
+
+    /// Measures every level in `collection`.
+    pub fn of(collection: &QuestionCollection) -> Self {
+        let levels = collection.iter().map(LevelStats::new).collect::<Vec<_>>();
+        Self::new(&levels)
+    }
+
+    pub fn to_json(&self) -> String {
+        json::Object::new()
+            .number("levels", self.levels)
+            .number("boxes", self.boxes)
+            .number("max_boxes", self.max_boxes)
+            .number("max_width", self.max_width)
+            .number("max_height", self.max_height)
+            .number("mean_reachable", format!("{:.2}", self.mean_reachable))
+            .number("mean_dead", format!("{:.2}", self.mean_dead))
+            .number("goal_rooms", self.goal_rooms)
+            .number("unsolvable", self.unsolvable)
+            .number("push_lower_bound", self.push_lower_bound)
+            .to_string()
+    }
+}
+
+/// Returns true if `pos` has walls on both sides, across or up and down.
+fn is_tunnel(board: &Board, pos: usize) -> bool {
+    let wall = |dir: Dir| {
+        board
+            .step(pos, dir, 1)
+            .is_none_or(|pos| board.square_at(pos).is_wall())
+    };
+    (wall(Dir::West) && wall(Dir::East)) || (wall(Dir::North) && wall(Dir::South))
+}
+
+/// Returns the squares that can be walked to from `start` over squares in `inside`.
+fn component(board: &Board, start: usize, inside: impl Fn(usize) -> bool) -> HashSet<usize> {
+    let mut seen = HashSet::from([start]);
+    let mut bag = vec![start];
+    while let Some(pos) = bag.pop() {
+        for next in board.borders(pos) {
+            if inside(next) && seen.insert(next) {
+                bag.push(next);
+            }
+        }
+    }
+    seen
+}
+
+/// Splits `squares` into groups of squares that are next to each other.
+fn components(board: &Board, squares: &HashSet<usize>) -> Vec<HashSet<usize>> {
+    let mut left = squares.clone();
+    let mut groups = vec![];
+    while let Some(&start) = left.iter().min() {
+        let group = component(board, start, |pos| left.contains(&pos));
+        left.retain(|pos| !group.contains(pos));
+        groups.push(group);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn stats() {
+        let question = Question::from_str(
+            "#########\n#   #...#\n# $ #   #\n# $     #\n# $ #   #\n#@  #####\n#####",
+        )
+        .unwrap();
+        let stats = LevelStats::new(&question);
+        assert_eq!((stats.width, stats.height, stats.boxes), (9, 7, 3));
+        assert_eq!((stats.floor, stats.reachable), (28, 28));
+        assert_eq!((stats.tunnels, stats.rooms), (1, 2));
+        assert!(stats.goal_room);
+        assert!(stats.dead > 0);
+        assert_eq!(stats.push_lower_bound, Some(17));
+
+        let collection = CollectionStats::new([&stats, &stats]);
+        assert_eq!((collection.levels, collection.boxes), (2, 6));
+        assert_eq!(collection.push_lower_bound, 34);
+    }
+}
@@ -0,0 +1,199 @@
+//! Feeds the level and solution parsers broken input, and plays whatever they accept,
+//! checking that nothing panics. The targets in `fuzz/` do the same with cargo-fuzz.
+
+use std::panic;
+use std::str::FromStr;
+use std::time::Duration;
+
+use sokoban::generate::Rng;
+use sokoban::question::{Question, QuestionCollection, Symmetry};
+use sokoban::reader::{slc, sok};
+use sokoban::solve::{self, Algorithm, Solution, SolverConfig};
+use sokoban::stats::LevelStats;
+
+/// Input that has broken the parsers before, or is close to something that would.
+const CORPUS: [&str; 34] = [
+    "",
+    "\n\n\n",
+    "@",
+    "$",
+    "#",
+    "@@",
+    "#$#\n#@#",
+    "#####\n#@$.#\n#####\n\nTitle:",
+    "#####\n#@$.#\n#",
+    "|||",
+    "5#|#@$.#|5#",
+    "99999999999999999999#|#@$.#",
+    "4294967296#|#@$.#",
+    "3(#@)|$.",
+    "((((((((((",
+    ")))",
+    "#@$.#\n\n#@$.#\n",
+    "Title: a\n\n#@$.#",
+    "<SokobanLevels><LevelCollection><Level><L>#@$.#</L></Level></LevelCollection></SokobanLevels>",
+    "<SokobanLevels><LevelCollection><Level><L></L></Level>",
+    "<SokobanLevels><",
+    "<<<>>>",
+    "::::\n#@$.#",
+    "@\u{0}$.\u{ffff}",
+    "@$.",
+    ".$@",
+    "@\n$\n.",
+    ".\n$\n@",
+    "*@",
+    "+$",
+    "$@$\n. .",
+    "@$$..",
+    "-@-$-.-",
+    "#@$.#\n#$.#",
+];
+
+/// Characters that mean something to one of the formats.
+const ALPHABET: &str = "#@+$*. -_|\n\r\t0123456789()uldrULDR:<>/=\"!;";
+
+/// Parses `text` every way there is, and plays with every level it gives.
+fn check(text: &str) {
+    if let Ok(question) = Question::from_str(text) {
+        check_level(&question);
+    }
+    let collections = [
+        QuestionCollection::from_str(text),
+        sok::parse(text),
+        slc::parse(text),
+    ];
+    for collection in collections.into_iter().flatten() {
+        for question in collection.iter() {
+            check_level(question);
+        }
+        let _ = collection.to_string();
+        let _ = collection.to_rle();
+    }
+
+    if let Ok(solution) = Solution::from_str(text) {
+        let question = Question::from_str("#######\n#     #\n#@$ $.#\n#   . #\n#######").unwrap();
+        let _ = solution.verify(&question);
+        let _ = solution.transform(Symmetry::Rotate90).verify(&question);
+    }
+}
+
+fn check_level(question: &Question) {
+    let _ = Question::from_str(&question.to_string());
+    let _ = question.simplify();
+    let _ = LevelStats::new(question);
+    if question.validate().is_err() {
+        return;
+    }
+    let config = SolverConfig {
+        algorithm: Algorithm::AStar,
+        max_nodes: Some(50),
+        time_limit: Some(Duration::from_millis(20)),
+        ..SolverConfig::default()
+    };
+    if let Some(solution) = solve::solve_puzzle(question, &config).solution {
+        assert_eq!(solution.verify(question), Ok(()), "{}", question);
+    }
+}
+
+/// Changes a few characters of `text` at random.
+fn mutate(text: &str, rng: &mut Rng) -> String {
+    let alphabet = ALPHABET.chars().collect::<Vec<_>>();
+    let mut chars = text.chars().collect::<Vec<_>>();
+    for _ in 0..1 + rng.below(4) {
+        let idx = rng.below(chars.len() + 1);
+        let c = *rng.choose(&alphabet).unwrap();
+        match rng.below(4) {
+            0 if idx < chars.len() => chars[idx] = c,
+            1 if idx < chars.len() => {
+                chars.remove(idx);
+            }
+            2 => {
+                let len = rng.below(chars.len() - idx + 1);
+                let copy = chars[idx..idx + len].to_vec();
+                chars.splice(idx..idx, copy);
+            }
+            _ => chars.insert(idx, c),
+        }
+    }
+    chars.into_iter().collect()
+}
+
+#[test]
+fn corpus() {
+    for text in CORPUS {
+        if panic::catch_unwind(|| check(text)).is_err() {
+            panic!("panicked on {:?}", text);
+        }
+    }
+
+    // Small enough to read, but far too big once the runs are expanded.
+    let huge = format!("{}\n", "999#".repeat(300)).repeat(300);
+    assert!(Question::from_str(&huge).is_err());
+    assert!(Question::from_str(&"1000#".repeat(2000)).is_err());
+
+    // Deep enough to overflow the stack if every element were parsed recursively.
+    let nested = format!("{}{}", "<a>".repeat(200_000), "</a>".repeat(200_000));
+    assert!(slc::parse(&nested).is_err());
+    let nested = format!(
+        "<SokobanLevels>{}<L>#@$.#</L>{}</SokobanLevels>",
+        "<a>".repeat(1000),
+        "</a>".repeat(1000)
+    );
+    assert!(slc::parse(&nested).is_err());
+}
+
+#[test]
+fn mutated_levels() {
+    let original = QuestionCollection::from_str(include_str!("../original.txt")).unwrap();
+    let seeds = [
+        original[0].to_string(),
+        format!("Title: x\n\n{}\n\n{}", original[1], original[2]),
+        original[5].to_string().replace('\n', "|"),
+        original[3].to_rle(),
+        "RRurDllLUdr".to_string(),
+    ];
+
+    let mut rng = Rng::new(50);
+    for _ in 0..150 {
+        let seed = rng.choose(&seeds).unwrap();
+        let text = mutate(seed, &mut rng);
+        if panic::catch_unwind(|| check(&text)).is_err() {
+            panic!("panicked on {:?}", text);
+        }
+    }
+}
+
+#[test]
+fn random_text() {
+    let alphabet = ALPHABET.chars().collect::<Vec<_>>();
+    let mut rng = Rng::new(5050);
+    for _ in 0..2000 {
+        let len = rng.below(40);
+        let text = (0..len)
+            .map(|_| *rng.choose(&alphabet).unwrap())
+            .collect::<String>();
+        if panic::catch_unwind(|| check(&text)).is_err() {
+            panic!("panicked on {:?}", text);
+        }
+    }
+}
+
+#[test]
+fn random_grids() {
+    let squares = "#### ...$$*@+-".chars().collect::<Vec<_>>();
+    let mut rng = Rng::new(505);
+    for _ in 0..5000 {
+        let (width, height) = (1 + rng.below(6), 1 + rng.below(5));
+        let text = (0..height)
+            .map(|_| {
+                (0..rng.below(width + 1))
+                    .map(|_| *rng.choose(&squares).unwrap())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        if panic::catch_unwind(|| check(&text)).is_err() {
+            panic!("panicked on {:?}", text);
+        }
+    }
+}
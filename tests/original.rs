@@ -0,0 +1,180 @@
+//! Solves levels from the bundled `original.txt` and checks the solutions by playing
+//! them, so that changes to the solver that break it show up.
+
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+use sokoban::question::{Position, Question, QuestionCollection, Square};
+use sokoban::reader;
+use sokoban::solve::{self, Algorithm, SolveStatus, SolverConfig};
+
+/// Levels of the collection with the fewest pushes they can be solved in.
+const OPTIMAL_PUSHES: [(usize, usize); 1] = [(1, 97)];
+
+/// Levels reduced to their first few boxes and targets (see `reduce`), with the fewest
+/// pushes they can then be solved in. Small enough to solve on every test run.
+const REDUCED_OPTIMAL_PUSHES: [(usize, usize, usize); 10] = [
+    (2, 2, 24),
+    (4, 2, 15),
+    (6, 2, 20),
+    (7, 2, 10),
+    (9, 2, 24),
+    (14, 2, 11),
+    (34, 2, 15),
+    (38, 2, 20),
+    (50, 2, 5),
+    (50, 3, 14),
+];
+
+fn original() -> QuestionCollection {
+    reader::read_path("original.txt").unwrap()
+}
+
+fn config(algorithm: Algorithm, seconds: u64) -> SolverConfig {
+    SolverConfig {
+        algorithm,
+        time_limit: Some(Duration::from_secs(seconds)),
+        ..SolverConfig::default()
+    }
+}
+
+#[test]
+fn reads_metadata() {
+    let collection = original();
+    assert_eq!(collection.len(), 50);
+    for (idx, question) in collection.iter().enumerate() {
+        assert_eq!(
+            question.title(),
+            Some(format!("Level {}", idx + 1).as_str())
+        );
+        assert_eq!(question.get_metadata("Author"), Some("Thinking Rabbit"));
+        assert_eq!(question.boxes().len(), question.targets().len());
+        assert!(solve::push_lower_bound(question).is_some());
+    }
+}
+
+#[test]
+fn solves_level_1() {
+    let question = &original()[0];
+    let result = solve::solve_puzzle(question, &config(Algorithm::Greedy, 60));
+    assert_eq!(result.status, SolveStatus::Solved);
+
+    let solution = result.solution.unwrap();
+    assert_eq!(solution.verify(question), Ok(()));
+    assert!(solution.push_count() >= OPTIMAL_PUSHES[0].1);
+}
+
+/// The classic levels need too many pushes to solve optimally here, so this solves
+/// them with only a few of their boxes and targets left, and checks that A* needs as
+/// few pushes as a plain search of every position.
+#[test]
+fn astar_is_push_optimal_on_reduced_levels() {
+    let collection = original();
+    for idx in [0, 1, 5, 6] {
+        let question = reduce(&collection[idx], 2);
+        let expected = fewest_pushes(&question);
+        let result = solve::solve_puzzle(&question, &config(Algorithm::AStar, 60));
+        match expected {
+            Some(pushes) => {
+                let solution = result.solution.unwrap();
+                assert_eq!(solution.verify(&question), Ok(()), "level {}", idx + 1);
+                assert_eq!(solution.push_count(), pushes, "level {}", idx + 1);
+            }
+            None => assert_eq!(result.status, SolveStatus::Unsolvable, "level {}", idx + 1),
+        }
+    }
+}
+
+#[test]
+fn known_optimal_push_counts_of_reduced_levels() {
+    let collection = original();
+    for (level, boxes, pushes) in REDUCED_OPTIMAL_PUSHES {
+        let question = reduce(&collection[level - 1], boxes);
+        let result = solve::solve_puzzle(&question, &config(Algorithm::AStar, 30));
+        let solution = result.solution.expect("no solution found in time");
+        assert_eq!(solution.verify(&question), Ok(()), "level {}", level);
+        assert_eq!(solution.push_count(), pushes, "level {}", level);
+    }
+}
+
+/// Takes a few minutes even in a release build: `cargo test --release -- --ignored`.
+#[test]
+#[ignore]
+fn known_optimal_push_counts() {
+    let collection = original();
+    for (level, pushes) in OPTIMAL_PUSHES {
+        let question = &collection[level - 1];
+        let result = solve::solve_puzzle(question, &config(Algorithm::AStar, 1800));
+        let solution = result.solution.expect("no solution found in time");
+        assert_eq!(solution.verify(question), Ok(()), "level {}", level);
+        assert_eq!(solution.push_count(), pushes, "level {}", level);
+    }
+}
+
+/// Returns `question` with only the first `boxes` boxes and targets, in reading order.
+fn reduce(question: &Question, boxes: usize) -> Question {
+    let first = |set: &HashSet<Position>| {
+        let mut positions = set.iter().copied().collect::<Vec<_>>();
+        positions.sort_by_key(|pos| (pos.y(), pos.x()));
+        positions.into_iter().take(boxes).collect()
+    };
+    let grid = question.rows().cloned().collect();
+    Question::from_parts(
+        grid,
+        first(question.boxes()),
+        first(question.targets()),
+        question.start(),
+    )
+    .unwrap()
+}
+
+/// Finds the fewest pushes that solve `question` by trying every position, with
+/// walking free and each push costing one. Returns `None` if it can't be solved.
+fn fewest_pushes(question: &Question) -> Option<usize> {
+    let floor = |pos: Position| {
+        question
+            .rows()
+            .nth(pos.y())
+            .and_then(|row| row.get(pos.x()))
+            .is_some_and(|sq| *sq == Square::Space)
+    };
+    let step = |pos: Position, (dx, dy): (isize, isize)| {
+        let x = pos.x().checked_add_signed(dx)?;
+        let y = pos.y().checked_add_signed(dy)?;
+        Some(Position::new(x, y)).filter(|&pos| floor(pos))
+    };
+    let key = |player: Position, boxes: &[Position]| {
+        let mut boxes = boxes.to_vec();
+        boxes.sort_by_key(|pos| (pos.y(), pos.x()));
+        (player.x(), player.y(), boxes)
+    };
+
+    let start = question.boxes().iter().copied().collect::<Vec<_>>();
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([(0, question.start(), start)]);
+    while let Some((pushes, player, boxes)) = queue.pop_front() {
+        if !seen.insert(key(player, &boxes)) {
+            continue;
+        }
+        if boxes.iter().all(|pos| question.targets().contains(pos)) {
+            return Some(pushes);
+        }
+        for dir in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+            let Some(next) = step(player, dir) else {
+                continue;
+            };
+            match boxes.iter().position(|&pos| pos == next) {
+                None => queue.push_front((pushes, next, boxes.clone())),
+                Some(idx) => {
+                    let Some(to) = step(next, dir).filter(|to| !boxes.contains(to)) else {
+                        continue;
+                    };
+                    let mut boxes = boxes.clone();
+                    boxes[idx] = to;
+                    queue.push_back((pushes + 1, next, boxes));
+                }
+            }
+        }
+    }
+    None
+}